@@ -0,0 +1,55 @@
+use std::time::{Duration, Instant};
+
+use ecs::{bundle::ComponentBundle, world::World};
+use ecs_derive::Component;
+
+#[derive(Component, Default)]
+struct Tag<const N: usize>;
+
+const ARCHETYPES: u32 = 100;
+const RUNS: u32 = 1_000;
+
+/// Builds a bundle holding `Tag::<N>` for every bit `N` set in `mask`
+fn bundle(mask: u32) -> ComponentBundle {
+    let mut bundle: ComponentBundle = ComponentBundle::default();
+    macro_rules! push_tags {
+        ($($bit:literal)*) => {
+            $(if mask & (1 << $bit) != 0 {
+                bundle.push(Tag::<$bit>);
+            })*
+        };
+    }
+    push_tags!(0 1 2 3 4 5 6);
+
+    bundle
+}
+
+fn main() {
+    let mut world: World = World::init();
+    for mask in 1..=ARCHETYPES {
+        world.spawn(bundle(mask)).unwrap();
+    }
+
+    bench(&world, "types", |world, idx| {
+        world.get_archetype(idx).unwrap().types().iter().count()
+    });
+    bench(&world, "types_ref", |world, idx| {
+        world.get_archetype(idx).unwrap().types_ref().iter().count()
+    });
+}
+
+fn bench<F: Fn(&World, usize) -> usize>(world: &World, name: &str, read: F) {
+    let start: Instant = Instant::now();
+    let mut types: usize = 0;
+    for _ in 0..RUNS {
+        for idx in 0..world.archetype_count() {
+            types += read(world, idx);
+        }
+    }
+    let elapsed: Duration = start.elapsed();
+
+    println!(
+        "{name}: read the types of {} archetypes {RUNS} times ({types} types) in {elapsed:?}",
+        world.archetype_count()
+    );
+}
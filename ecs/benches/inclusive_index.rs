@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+use ecs::{bundle::ComponentBundle, world::World};
+use ecs_derive::Component;
+
+#[derive(Component, Default)]
+struct Tag<const N: usize>;
+
+const CACHED: u32 = 50;
+const CONFIGURATIONS: u32 = 100;
+const RUNS: u32 = 100;
+
+/// Builds a bundle holding `Tag::<N>` for every bit `N` set in `mask`
+fn bundle(mask: u32) -> ComponentBundle {
+    let mut bundle: ComponentBundle = ComponentBundle::default();
+    macro_rules! push_tags {
+        ($($bit:literal)*) => {
+            $(if mask & (1 << $bit) != 0 {
+                bundle.push(Tag::<$bit>);
+            })*
+        };
+    }
+    push_tags!(0 1 2 3 4 5 6 7 8 9 10 11 12);
+
+    bundle
+}
+
+fn main() {
+    let mut spawning: Duration = Duration::ZERO;
+    for _ in 0..RUNS {
+        let mut world: World = World::init();
+        // every new archetype caches its own types as an inclusive query
+        for mask in (1..=CACHED).map(|idx| idx << 7) {
+            world.spawn(bundle(mask)).unwrap();
+        }
+
+        let start: Instant = Instant::now();
+        for mask in 1..=CONFIGURATIONS {
+            world.spawn(bundle(mask)).unwrap();
+        }
+        spawning += start.elapsed();
+
+        assert!(world.archetype_count() == (1 + CACHED + CONFIGURATIONS) as usize);
+    }
+
+    println!(
+        "spawned {CONFIGURATIONS} archetypes over {CACHED} cached queries in {:?}",
+        spawning / RUNS
+    );
+}
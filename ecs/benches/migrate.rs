@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use ecs::{
+    archetype::{Archetype, Migration},
+    bundle::ComponentBundle,
+    entity::EntityId,
+    world::World,
+};
+use ecs_derive::Component;
+
+#[derive(Component, Default)]
+struct Position {
+    _x: f64,
+    _y: f64,
+}
+
+#[derive(Component, Default)]
+struct IsAggro;
+
+const ENTITIES: usize = 10_000;
+
+fn setup() -> (World, Vec<EntityId>) {
+    let mut world: World = World::init();
+    let entities: Vec<EntityId> = (0..ENTITIES)
+        .map(|_| {
+            world
+                .spawn(ComponentBundle::default().insert(Position::default()))
+                .unwrap()
+        })
+        .collect();
+
+    (world, entities)
+}
+
+fn bench<F: FnMut(&mut World, &[EntityId])>(name: &str, mut migrate: F) {
+    let (mut world, entities) = setup();
+
+    let start: Instant = Instant::now();
+    migrate(&mut world, &entities);
+    let elapsed: Duration = start.elapsed();
+
+    let archetype: &Archetype = world.get_archetype_for_entity_ref(entities[0]).unwrap();
+    assert!(archetype.len() == ENTITIES);
+    println!("{name}: migrated {ENTITIES} entities in {elapsed:?}");
+}
+
+fn main() {
+    bench("single", |world, entities| {
+        for &entity in entities.iter() {
+            world
+                .migrate(entity, Migration::Add1(IsAggro.into()))
+                .unwrap();
+        }
+    });
+
+    bench("batch", |world, entities| {
+        world
+            .migrate_many(entities, || Migration::Add1(IsAggro.into()))
+            .unwrap();
+    });
+}
@@ -1,23 +1,179 @@
-use core::panic;
-
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, parse_quote, spanned::Spanned, DeriveInput};
 
-#[proc_macro_derive(Component)]
+#[proc_macro_derive(Component, attributes(component))]
 pub fn derive_component(input: TokenStream) -> TokenStream {
     let input: DeriveInput = parse_macro_input!(input as DeriveInput);
 
+    let mut allow_drop: bool = false;
+    let mut storage = format_ident!("Dense");
+    for attr in input.attrs.iter() {
+        if !attr.path().is_ident("component") {
+            continue;
+        }
+
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("allow_drop") {
+                allow_drop = true;
+                Ok(())
+            } else if meta.path.is_ident("storage") {
+                let kind: syn::LitStr = meta.value()?.parse()?;
+                storage = match kind.value().as_str() {
+                    "dense" => format_ident!("Dense"),
+                    "sparse" => format_ident!("Sparse"),
+                    _ => {
+                        return Err(syn::Error::new(
+                            kind.span(),
+                            "expected `storage = \"dense\"` or `storage = \"sparse\"`",
+                        ))
+                    }
+                };
+                Ok(())
+            } else {
+                Err(meta.error("unsupported component attribute"))
+            }
+        });
+
+        if let Err(err) = parsed {
+            return err.into_compile_error().into();
+        }
+    }
+
     let name = input.ident;
 
+    // every type parameter ends up stored in a `ComponentStore`, so it must be thread-safe too
+    let mut generics: syn::Generics = input.generics;
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(Send));
+        param.bounds.push(parse_quote!(Sync));
+        param.bounds.push(parse_quote!('static));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // components are swap-removed between stores, so explicit `Drop` impls fire at surprising
+    // times. the blanket impl below conflicts with the concrete one for any type implementing `Drop`
+    let drop_check = if allow_drop {
+        quote! {}
+    } else {
+        quote! {
+            const _: () = {
+                #[allow(non_camel_case_types)]
+                trait ComponentMustNotImplementDrop_UseComponentAllowDrop {}
+                #[allow(drop_bounds)]
+                impl<T: Drop> ComponentMustNotImplementDrop_UseComponentAllowDrop for T {}
+                impl #impl_generics ComponentMustNotImplementDrop_UseComponentAllowDrop
+                    for #name #ty_generics #where_clause {}
+            };
+        }
+    };
+
+    // surface missing thread-safety bounds at the derive site, named so the note reads as advice
+    let thread_check = quote! {
+        const _: () = {
+            fn component_types_must_be_send_sync_and_static<T: Send + Sync + 'static>() {}
+            #[allow(dead_code)]
+            fn check #impl_generics () #where_clause {
+                component_types_must_be_send_sync_and_static::<#name #ty_generics>();
+            }
+        };
+    };
+
     let expanded = quote! {
-        impl ecs::component::Component for #name {
+        #drop_check
+        #thread_check
+
+        impl #impl_generics ecs::component::Component for #name #ty_generics #where_clause {
             fn to_any(self: Box<Self>) -> Box<dyn std::any::Any> {
                 self
             }
             fn to_store(self: Box<Self>) -> ecs::component::ComponentStore {
                 (*self).into()
             }
+            fn register_name() {
+                ecs::bundle::TypeBundle::register_name::<Self>(stringify!(#name));
+            }
+            fn clone_box(&self) -> Option<ecs::component::ComponentBox> {
+                #[allow(unused_imports)]
+                use ecs::component::{ProbeClone as _, ProbeNoClone as _};
+                (&ecs::component::CloneProbe(self)).probe_clone_box()
+            }
+            fn storage_kind() -> ecs::component::StorageKind {
+                ecs::component::StorageKind::#storage
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = parse_macro_input!(input as DeriveInput);
+
+    let name = input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match input.data {
+        syn::Data::Struct(data) => data.fields,
+        _ => {
+            return syn::Error::new_spanned(&name, "Bundle can only be derived for structs")
+                .into_compile_error()
+                .into();
+        }
+    };
+
+    let mut accessors: Vec<_> = Vec::new();
+    let mut field_checks: Vec<_> = Vec::new();
+    for (pos, field) in fields.iter().enumerate() {
+        if let syn::Type::Reference(_) = &field.ty {
+            return syn::Error::new_spanned(
+                field,
+                "Bundle fields must own their components, found a reference",
+            )
+            .into_compile_error()
+            .into();
+        }
+
+        accessors.push(match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let idx = syn::Index::from(pos);
+                quote! { #idx }
+            }
+        });
+
+        // point missing `Component` impls at the offending field rather than the generated insert
+        let field_type = &field.ty;
+        field_checks.push(quote_spanned! {field_type.span()=>
+            bundle_fields_must_implement_component::<#field_type>();
+        });
+    }
+
+    let expanded = quote! {
+        const _: () = {
+            fn bundle_fields_must_implement_component<T: ecs::component::Component>() {}
+            #[allow(dead_code)]
+            fn check #impl_generics () #where_clause {
+                #(#field_checks)*
+            }
+        };
+
+        impl #impl_generics From<#name #ty_generics> for ecs::bundle::ComponentBundle #where_clause {
+            fn from(value: #name #ty_generics) -> Self {
+                ecs::bundle::ComponentBundle::default()
+                    #(.insert(value.#accessors))*
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Spawns a new entity holding every field of the bundle
+            pub fn spawn(
+                self,
+                world: &mut ecs::world::World,
+            ) -> Result<ecs::entity::EntityId, ecs::errors::EcsError> {
+                world.spawn(self.into())
+            }
         }
     };
 
@@ -29,14 +185,89 @@ pub fn derive_query_model(input: TokenStream) -> TokenStream {
     let input: DeriveInput = parse_macro_input!(input as DeriveInput);
 
     let name = input.ident;
-    let fields = match input.data {
+
+    // implement over the declared lifetime so `Self` and `Row` share the user's naming
+    let lifetime = input
+        .generics
+        .lifetimes()
+        .next()
+        .map(|def| def.lifetime.clone());
+    let (impl_generics, self_type) = match &lifetime {
+        Some(lifetime) => (quote! { <#lifetime> }, quote! { #name<#lifetime> }),
+        None => (quote! {}, quote! { #name }),
+    };
+
+    let (fields, named) = match input.data {
         syn::Data::Struct(data) => match data.fields {
-            syn::Fields::Named(fields) => fields.named,
-            _ => panic!("Expected to be a named struct"),
+            syn::Fields::Named(fields) => (fields.named.into_iter().collect::<Vec<_>>(), true),
+            syn::Fields::Unnamed(fields) => (fields.unnamed.into_iter().collect::<Vec<_>>(), false),
+            syn::Fields::Unit => (Vec::new(), false),
         },
-        _ => panic!("Expected to be a struct"),
+        _ => {
+            return syn::Error::new_spanned(&name, "QueryModel can only be derived for structs")
+                .into_compile_error()
+                .into();
+        }
     };
 
+    // a query without fields reads nothing, so it matches every archetype and never runs
+    if fields.is_empty() {
+        let expanded = quote! {
+            impl ecs::query::QueryModel for #name {
+                type Row<'r> = #name;
+
+                fn get_types() -> ecs::bundle::TypeBundle {
+                    ecs::bundle::TypeBundle::default()
+                }
+
+                fn get_reads(_at: &ecs::archetype::Archetype) -> Vec<ecs::component::ReadGuard<'_>> {
+                    Vec::new()
+                }
+
+                fn get_writes(_at: &ecs::archetype::Archetype) -> Vec<ecs::component::WriteGuard<'_>> {
+                    Vec::new()
+                }
+
+                fn process<F>(
+                    _reads: Vec<ecs::component::ReadGuard>,
+                    _writes: Vec<ecs::component::WriteGuard>,
+                    _system: &mut F,
+                ) -> usize
+                where
+                    for<'f> F: FnMut(Self::Row<'f>),
+                {
+                    0
+                }
+
+                fn process_rows<F>(
+                    _reads: Vec<ecs::component::ReadGuard>,
+                    _writes: Vec<ecs::component::WriteGuard>,
+                    _rows: &[usize],
+                    _system: &mut F,
+                ) -> usize
+                where
+                    for<'f> F: FnMut(Self::Row<'f>),
+                {
+                    0
+                }
+
+                fn process_parallel<F>(
+                    _reads: Vec<ecs::component::ReadGuard>,
+                    _writes: Vec<ecs::component::WriteGuard>,
+                    _chunk_size: usize,
+                    _system: &F,
+                ) -> usize
+                where
+                    for<'f> F: Fn(Self::Row<'f>) + Send + Sync,
+                {
+                    0
+                }
+            }
+        };
+
+        return proc_macro::TokenStream::from(expanded);
+    }
+
     let mut field_types: Vec<_> = Vec::new();
 
     let mut ref_names: Vec<_> = Vec::new();
@@ -46,73 +277,281 @@ pub fn derive_query_model(input: TokenStream) -> TokenStream {
     let mut ref_elems: Vec<_> = Vec::new();
     let mut mut_elems: Vec<_> = Vec::new();
 
-    for field in fields.iter() {
-        if let syn::Type::Reference(ty) = &field.ty {
-            field_types.push(&ty.elem);
-            let ident_clone = field.ident.clone();
-            names.push(ident_clone.unwrap());
+    let mut excluded_types: Vec<_> = Vec::new();
+    let mut changed_types: Vec<_> = Vec::new();
+
+    // each field's value in the row, in declaration order so positional structs line up
+    let mut row_fields: Vec<_> = Vec::new();
+
+    for (pos, field) in fields.iter().enumerate() {
+        // positional fields are bound to generated names `_0`, `_1`, ...
+        let field_name = match &field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("_{}", pos),
+        };
 
-            if ty.mutability.is_none() {
-                ref_names.push(&field.ident);
-                ref_elems.push(&ty.elem);
+        // `Without<T>` markers only exclude archetypes, and hold no data in the row
+        if let Some(excluded) = marker_type(&field.ty, "Without") {
+            excluded_types.push(excluded);
+            row_fields.push(if named {
+                quote! { #field_name: ecs::query::Without::new() }
             } else {
-                mut_names.push(&field.ident);
-                mut_elems.push(&ty.elem);
-            }
+                quote! { ecs::query::Without::new() }
+            });
+            continue;
         }
+
+        // `Changed<&T>` fields behave like their inner reference, filtered to changed rows
+        let changed: Option<&syn::Type> = marker_type(&field.ty, "Changed");
+        let field_ty: &syn::Type = changed.unwrap_or(&field.ty);
+
+        let syn::Type::Reference(ty) = field_ty else {
+            let field_type = &field.ty;
+            let field_label = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => pos.to_string(),
+            };
+            let message = format!(
+                "QueryModel fields must be references (&T or &mut T), found owned field `{}: {}`",
+                field_label,
+                quote!(#field_type)
+            );
+            return syn::Error::new_spanned(field, message)
+                .into_compile_error()
+                .into();
+        };
+
+        field_types.push(&ty.elem);
+        names.push(field_name.clone());
+
+        let value = if ty.mutability.is_none() {
+            ref_names.push(field_name.clone());
+            ref_elems.push(&ty.elem);
+            quote! { &#field_name[idx] }
+        } else {
+            mut_names.push(field_name.clone());
+            mut_elems.push(&ty.elem);
+            quote! { &mut #field_name[idx] }
+        };
+        let value = if changed.is_some() {
+            changed_types.push(&ty.elem);
+            quote! { ecs::query::Changed::new(#value) }
+        } else {
+            value
+        };
+
+        row_fields.push(if named {
+            quote! { #field_name: #value }
+        } else {
+            value
+        });
     }
 
-    let first_name = names[0].clone();
+    let Some(first_name) = names.first().cloned() else {
+        return syn::Error::new_spanned(
+            &name,
+            "QueryModel needs at least one reference field alongside `Without` markers",
+        )
+        .into_compile_error()
+        .into();
+    };
 
     let mut_iter_names = mut_names.iter().rev();
     let mut_iter_elems = mut_elems.iter().rev();
 
     let ref_idx: Vec<_> = ref_elems.iter().enumerate().map(|(idx, _)| idx).collect();
 
+    // avoid unused bindings in the generated `process` when a query has no reads or no writes
+    let reads_arg = if ref_names.is_empty() {
+        quote! { _reads }
+    } else {
+        quote! { reads }
+    };
+    let writes_arg = if mut_names.is_empty() {
+        quote! { _writes }
+    } else {
+        quote! { mut writes }
+    };
+
+    let row_expr = if named {
+        quote! { #name { #(#row_fields,)* } }
+    } else {
+        quote! { #name(#(#row_fields,)*) }
+    };
+
+    let bindings = quote! {
+        #(let #ref_names = reads[#ref_idx]
+            .to_any()
+            .downcast_ref::<Vec<#ref_elems>>()
+            .expect(concat!("read storage does not hold component ", stringify!(#ref_elems)));)
+        *
+
+        #(let mut temp = writes
+            .pop()
+            .expect(concat!("missing write storage for component ", stringify!(#mut_iter_elems)));
+        let #mut_iter_names = temp.to_any_mut()
+            .downcast_mut::<Vec<#mut_iter_elems>>()
+            .expect(concat!("write storage does not hold component ", stringify!(#mut_iter_elems)));)
+        *
+    };
+
+    let row = quote! {
+        let row: Self::Row<'_> = #row_expr;
+
+        system(row);
+    };
+
+    // stable proc macros cannot emit notes, so the expected types are listed on the impl instead
+    let expected_doc = format!(
+        "Matches archetypes holding {}",
+        field_types
+            .iter()
+            .map(|ty| format!("`{}`", quote!(#ty)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
     let expanded = quote! {
-        impl ecs::query::QueryModel for #name<'_> {
+        #[doc = #expected_doc]
+        impl #impl_generics ecs::query::QueryModel for #self_type {
             type Row<'r> = #name<'r>;
 
             fn get_types() -> ecs::bundle::TypeBundle {
                 ecs::bundle::TypeBundle::from([#(std::any::TypeId::of::<#field_types>()), *].as_slice())
             }
 
-            fn get_reads(at: &ecs::archetype::Archetype) -> Vec<ecs::component::ReadGuard> {
-                vec![#(at.get_storage(std::any::TypeId::of::<#ref_elems>()).unwrap().inner()), *]
+            fn get_excluded() -> ecs::bundle::TypeBundle {
+                ecs::bundle::TypeBundle::from([#(std::any::TypeId::of::<#excluded_types>()), *].as_slice())
+            }
+
+            fn get_changed_types() -> ecs::bundle::TypeBundle {
+                ecs::bundle::TypeBundle::from([#(std::any::TypeId::of::<#changed_types>()), *].as_slice())
+            }
+
+            fn get_write_types() -> ecs::bundle::TypeBundle {
+                ecs::bundle::TypeBundle::from([#(std::any::TypeId::of::<#mut_elems>()), *].as_slice())
             }
 
-            fn get_writes(at: &ecs::archetype::Archetype) -> Vec<ecs::component::WriteGuard> {
-                vec![#(at.get_storage(std::any::TypeId::of::<#mut_elems>()).unwrap().inner_mut()), *]
+            fn get_sparse_types() -> ecs::bundle::TypeBundle {
+                let types: Vec<std::any::TypeId> = [#((
+                    std::any::TypeId::of::<#field_types>(),
+                    <#field_types as ecs::component::Component>::storage_kind(),
+                )), *]
+                .into_iter()
+                .filter(|(_, kind)| *kind == ecs::component::StorageKind::Sparse)
+                .map(|(type_id, _)| type_id)
+                .collect();
+
+                ecs::bundle::TypeBundle::from(types.as_slice())
+            }
+
+            fn get_reads(at: &ecs::archetype::Archetype) -> Vec<ecs::component::ReadGuard<'_>> {
+                vec![#(at
+                    .get_storage(std::any::TypeId::of::<#ref_elems>())
+                    .expect(concat!("Archetype missing component ", stringify!(#ref_elems)))
+                    .inner()), *]
+            }
+
+            fn get_writes(at: &ecs::archetype::Archetype) -> Vec<ecs::component::WriteGuard<'_>> {
+                vec![#(at
+                    .get_storage(std::any::TypeId::of::<#mut_elems>())
+                    .expect(concat!("Archetype missing component ", stringify!(#mut_elems)))
+                    .inner_mut()), *]
             }
 
             fn process<F>(
-                reads: Vec<ecs::component::ReadGuard>,
-                mut writes: Vec<ecs::component::WriteGuard>,
+                #reads_arg: Vec<ecs::component::ReadGuard>,
+                #writes_arg: Vec<ecs::component::WriteGuard>,
                 system: &mut F,
-            ) where
+            ) -> usize
+            where
                 for<'f> F: FnMut(Self::Row<'f>),
             {
-                #(let #ref_names = reads[#ref_idx]
-                    .to_any()
-                    .downcast_ref::<Vec<#ref_elems>>()
-                    .unwrap();)
-                *
-
-                #(let mut temp = writes.pop().unwrap();
-                let #mut_iter_names = temp.to_any_mut()
-                    .downcast_mut::<Vec<#mut_iter_elems>>()
-                    .unwrap();)
-                *
-
-                for idx in 0..#first_name.len() {
-                    let row: Self::Row<'_> = #name { #(#ref_names: &#ref_names[idx]), *, #(#mut_names: &mut #mut_names[idx]), * };
-
-                    system(row);
+                #bindings
+
+                let len: usize = #first_name.len();
+                if len == 0 {
+                    return 0;
+                }
+
+                for idx in 0..len {
+                    #row
                 }
+
+                len
             }
 
+            fn process_rows<F>(
+                #reads_arg: Vec<ecs::component::ReadGuard>,
+                #writes_arg: Vec<ecs::component::WriteGuard>,
+                rows: &[usize],
+                system: &mut F,
+            ) -> usize
+            where
+                for<'f> F: FnMut(Self::Row<'f>),
+            {
+                #bindings
+
+                for &idx in rows.iter() {
+                    #row
+                }
+
+                rows.len()
+            }
+
+            fn process_parallel<F>(
+                #reads_arg: Vec<ecs::component::ReadGuard>,
+                #writes_arg: Vec<ecs::component::WriteGuard>,
+                chunk_size: usize,
+                system: &F,
+            ) -> usize
+            where
+                for<'f> F: Fn(Self::Row<'f>) + Send + Sync,
+            {
+                #bindings
+
+                let len: usize = #first_name.len();
+                if len == 0 {
+                    return 0;
+                }
+
+                // split every storage at the same rows, so each chunk owns its rows exclusively
+                let chunk_size: usize = chunk_size.max(1);
+                #(let mut #ref_names = #ref_names.chunks(chunk_size);)*
+                #(let mut #mut_names = #mut_names.chunks_mut(chunk_size);)*
+                let chunks: Vec<_> = (0..len.div_ceil(chunk_size))
+                    .map(|_| (#(#ref_names.next().unwrap(),)* #(#mut_names.next().unwrap(),)*))
+                    .collect();
+
+                ecs::query::for_each_chunk(chunks, |(#(#ref_names,)* #(#mut_names,)*)| {
+                    for idx in 0..#first_name.len() {
+                        #row
+                    }
+                });
+
+                len
+            }
         }
     };
 
     proc_macro::TokenStream::from(expanded)
 }
+
+/// Gets `T` if the given type is a `marker<T>`, such as `Without<T>`
+fn marker_type<'t>(ty: &'t syn::Type, marker: &str) -> Option<&'t syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != marker {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    match args.args.first()? {
+        syn::GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
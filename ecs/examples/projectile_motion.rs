@@ -17,6 +17,7 @@ struct Velocity {
     dy: f64,
 }
 
+#[allow(dead_code)]
 #[derive(Component, Debug, Default)]
 struct Mass(pub f32); // kilograms
 
@@ -1,21 +1,26 @@
 use std::{
     any::TypeId,
     collections::HashMap,
-    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
 };
 
 use crate::{
     bundle::{ComponentBundle, TypeBundle},
-    component::{ComponentBox, ComponentStore},
+    component::{ComponentBox, ComponentStore, WriteGuard},
     entity::EntityId,
     errors::StoreError,
 };
 
 pub struct Archetype {
     index: HashMap<TypeId, usize>,
+    types: TypeBundle,
     storage: Box<[ComponentStore]>,
     entities: RwLock<Vec<EntityId>>,
-    pub edges: HashMap<TypeId, usize>,
+    entity_count: AtomicUsize,
+    edges: HashMap<TypeId, usize>,
 }
 
 impl Archetype {
@@ -23,25 +28,132 @@ impl Archetype {
         self.entities().last().copied()
     }
 
-    pub fn entities(&self) -> RwLockReadGuard<Vec<EntityId>> {
+    pub fn entities(&self) -> RwLockReadGuard<'_, Vec<EntityId>> {
         self.entities.read().unwrap()
     }
 
-    fn entities_mut(&self) -> RwLockWriteGuard<Vec<EntityId>> {
+    fn entities_mut(&self) -> RwLockWriteGuard<'_, Vec<EntityId>> {
         self.entities.write().unwrap()
     }
 
-    pub fn get_storage(&self, type_id: TypeId) -> Result<&ComponentStore, StoreError> {
-        self.index
-            .get(&type_id)
-            .map(|&idx| &self.storage[idx])
-            .ok_or(StoreError::StorageNotFound)
+    fn push_entities(&self, entity_ids: &[EntityId]) {
+        self.entities_mut().extend_from_slice(entity_ids);
+        self.entity_count
+            .fetch_add(entity_ids.len(), Ordering::Relaxed);
+        for store in self.storage.iter() {
+            store.push_changed(entity_ids.len());
+        }
+    }
+
+    fn swap_remove_entity(&self, row: usize) -> EntityId {
+        let entity: EntityId = self.entities_mut().swap_remove(row);
+        self.entity_count.fetch_sub(1, Ordering::Relaxed);
+        for store in self.storage.iter() {
+            store.swap_remove_changed(row);
+        }
+        entity
+    }
+
+    /// Takes the changed flags of the given types, resetting them to unchanged
+    ///
+    /// A row is flagged only if every given type changed for it
+    pub fn take_changed(&self, types: &TypeBundle) -> Vec<bool> {
+        let mut changed: Vec<bool> = vec![true; self.len()];
+        for &type_id in types.iter() {
+            let Some(store) = self.get_storage(type_id) else {
+                return vec![false; self.len()];
+            };
+            changed
+                .iter_mut()
+                .zip(store.take_changed())
+                .for_each(|(row, store_row)| *row &= store_row);
+        }
+
+        changed
+    }
+
+    /// Marks the given rows as changed for every given type stored in the archetype
+    pub fn mark_changed(&self, types: &TypeBundle, rows: &[usize]) {
+        for &type_id in types.iter() {
+            if let Some(store) = self.get_storage(type_id) {
+                store.mark_changed(rows);
+            }
+        }
+    }
+
+    /// Marks every row as changed for every given type stored in the archetype
+    pub fn mark_all_changed(&self, types: &TypeBundle) {
+        for &type_id in types.iter() {
+            if let Some(store) = self.get_storage(type_id) {
+                store.mark_all_changed();
+            }
+        }
+    }
+
+    /// Gets the number of entities in the archetype without locking the entity list
+    pub fn entity_count(&self) -> usize {
+        self.entity_count.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entity_count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entity_count() == 0
+    }
+
+    pub fn get_storage(&self, type_id: TypeId) -> Option<&ComponentStore> {
+        self.index.get(&type_id).map(|&idx| &self.storage[idx])
+    }
+
+    /// Gets the archetype reached by adding or removing `type_id`, if it has been cached
+    pub fn get_edge(&self, type_id: TypeId) -> Option<usize> {
+        self.edges.get(&type_id).copied()
+    }
+
+    /// Caches the archetype reached by adding or removing `type_id`
+    pub(crate) fn set_edge(&mut self, type_id: TypeId, target: usize) {
+        self.edges.insert(type_id, target);
+    }
+
+    /// Gets the number of cached edges to other archetypes
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Moves every edge to the archetype index returned by `remap`, dropping those it maps to
+    /// `None` and returning how many were removed
+    pub(crate) fn remap_edges<F: FnMut(usize) -> Option<usize>>(&mut self, mut remap: F) -> usize {
+        let count: usize = self.edges.len();
+        self.edges.retain(|_, target| match remap(*target) {
+            Some(new_target) => {
+                *target = new_target;
+                true
+            }
+            None => false,
+        });
+
+        count - self.edges.len()
     }
 
     pub fn get_entity(&self, row: usize) -> Option<EntityId> {
         self.entities().get(row).copied()
     }
 
+    /// Gets the entities in rows `start..end`, acquiring the entity lock once
+    ///
+    /// The range is clamped to the entity list, so chunks may overrun the final row.
+    pub fn get_entities_in_range(&self, start: usize, end: usize) -> Vec<EntityId> {
+        let entities = self.entities();
+        let end: usize = end.min(entities.len());
+
+        entities
+            .get(start.min(end)..end)
+            .map(<[EntityId]>::to_vec)
+            .unwrap_or_default()
+    }
+
     pub fn new(bundle: ComponentBundle, entity_id: EntityId) -> Self {
         let mut index: HashMap<TypeId, usize> = HashMap::new();
         let mut storage: Vec<ComponentStore> = Vec::new();
@@ -51,95 +163,320 @@ impl Archetype {
         });
 
         Self {
+            types: (&index).into(),
             index,
             storage: storage.into(),
             entities: RwLock::new(Vec::from([entity_id])),
+            entity_count: AtomicUsize::new(1),
+            edges: HashMap::new(),
+        }
+    }
+
+    /// Creates a new `Archetype` from whole stores, one per type, with a row for each entity
+    ///
+    /// Fails if any store does not hold exactly one component per entity.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_stores(
+        stores: Vec<ComponentStore>,
+        entity_ids: Vec<EntityId>,
+    ) -> Result<Self, StoreError> {
+        if stores.iter().any(|store| store.len() != entity_ids.len()) {
+            return Err(StoreError::TypeMismatch);
+        }
+
+        let index: HashMap<TypeId, usize> = stores
+            .iter()
+            .enumerate()
+            .map(|(idx, store)| (store.inner_type_id(), idx))
+            .collect();
+        Ok(Self {
+            types: (&index).into(),
+            index,
+            storage: stores.into(),
+            entity_count: AtomicUsize::new(entity_ids.len()),
+            entities: RwLock::new(entity_ids),
             edges: HashMap::new(),
+        })
+    }
+
+    /// Creates a new `Archetype` holding the first entity, with room for `capacity` entities
+    /// before any of its storage reallocates
+    pub fn with_capacity(bundle: ComponentBundle, entity_id: EntityId, capacity: usize) -> Self {
+        let archetype: Self = Self::new(bundle, entity_id);
+        let additional: usize = capacity.saturating_sub(1);
+        archetype.entities_mut().reserve(additional);
+        for store in archetype.storage.iter() {
+            store.inner_mut().reserve(additional);
         }
+
+        archetype
     }
 
+    /// Creates a new `Archetype` with the same component layout and no entities
+    pub fn duplicate(&self) -> Self {
+        Self {
+            index: self.index.clone(),
+            types: self.types.clone(),
+            storage: self.storage.iter().map(|store| store.new_empty()).collect(),
+            entities: RwLock::new(Vec::new()),
+            entity_count: AtomicUsize::new(0),
+            edges: HashMap::new(),
+        }
+    }
+
+    /// Gets an owned copy of the types stored in the archetype, see `types_ref` to borrow them
     pub fn types(&self) -> TypeBundle {
-        (&self.index).into()
+        self.types.clone()
+    }
+
+    /// Gets the types stored in the archetype, cached when it was created
+    pub fn types_ref(&self) -> &TypeBundle {
+        &self.types
     }
 
     pub fn has_type(&self, type_id: TypeId) -> bool {
-        self.index.get(&type_id).is_some()
+        self.index.contains_key(&type_id)
     }
 
-    pub fn add(&self, bundle: ComponentBundle, entity_id: EntityId) -> usize {
+    /// Adds a bundle that must match this archetype's types exactly, returning its row
+    pub fn add(&self, bundle: ComponentBundle, entity_id: EntityId) -> Result<usize, StoreError> {
+        if bundle.types() != self.types {
+            return Err(StoreError::TypeMismatch);
+        }
+
         let row = self.entities().len();
         for comp in bundle.component_iter() {
             self.get_storage(comp.inner_type_id())
-                .unwrap()
+                .ok_or(StoreError::StorageNotFound)?
                 .inner_mut()
-                .push(comp)
-                .unwrap();
+                .push(comp)?;
+        }
+        self.push_entities(&[entity_id]);
+
+        Ok(row)
+    }
+
+    /// Adds many bundles matching this archetype's types, locking each storage only once
+    ///
+    /// Returns the row of the first added entity
+    pub fn add_batch<I>(&self, bundles: I, entity_ids: &[EntityId]) -> usize
+    where
+        I: IntoIterator<Item = ComponentBundle>,
+    {
+        let row: usize = self.len();
+        let mut guards: Vec<WriteGuard> =
+            self.storage.iter().map(|store| store.inner_mut()).collect();
+        for bundle in bundles.into_iter() {
+            for comp in bundle.component_iter() {
+                guards[self.index[&comp.inner_type_id()]]
+                    .push(comp)
+                    .unwrap();
+            }
         }
-        self.entities_mut().push(entity_id);
+        self.push_entities(entity_ids);
 
         row
     }
 
-    pub fn remove(&self, row: usize) -> EntityId {
-        let entity: EntityId = self.get_last_entity().unwrap();
+    /// Swap-removes the entity at `row`, returning the entity moved into its place along with
+    /// every removed component
+    ///
+    /// Fails before removing anything if `row` is out of bounds.
+    pub fn remove(&self, row: usize) -> Result<(EntityId, ComponentBundle), StoreError> {
+        if row >= self.len() {
+            return Err(StoreError::Placeholder);
+        }
+
+        let entity: EntityId = self.get_last_entity().ok_or(StoreError::Placeholder)?;
+        let mut bundle: ComponentBundle = ComponentBundle::default();
         for idx in self.index.values() {
-            self.storage[*idx].inner_mut().swap_remove(row);
+            bundle.push_box(
+                self.storage[*idx]
+                    .inner_mut()
+                    .swap_remove(row)
+                    .ok_or(StoreError::Placeholder)?,
+            );
         }
-        self.entities_mut().swap_remove(row);
-        entity
+        self.swap_remove_entity(row);
+
+        Ok((entity, bundle))
     }
 
-    pub fn migrate(&self, target: &mut Self, row: usize, op: Migration) -> (EntityId, usize) {
-        let moved: EntityId = self.get_last_entity().unwrap();
+    /// Removes every entity and drops its components, returning the removed entities in row order
+    ///
+    /// Edges are kept, so the archetype can be reused by later migrations.
+    pub fn clear(&mut self) -> Vec<EntityId> {
+        self.storage = self.storage.iter().map(|store| store.new_empty()).collect();
+        self.entity_count.store(0, Ordering::Relaxed);
+
+        std::mem::take(&mut *self.entities_mut())
+    }
+
+    /// Checks that every store moved by `op` has a compatible store in `target`
+    ///
+    /// Lets callers fail before any component is moved, rather than part way through a migration.
+    pub fn check_migration(&self, target: &Self, op: &Migration) -> Result<(), StoreError> {
+        let removed: Option<TypeId> = match op {
+            Migration::Remove(type_id) => Some(*type_id),
+            _ => None,
+        };
+        let moved: bool = self
+            .storage
+            .iter()
+            .filter(|store| Some(store.inner_type_id()) != removed)
+            .all(|store| {
+                target
+                    .get_storage(store.inner_type_id())
+                    .is_some_and(|target_store| store.is_compatible_with(target_store))
+            });
+        let added: bool =
+            removed.is_some() || op.types().iter().all(|&type_id| target.has_type(type_id));
+
+        if moved && added {
+            Ok(())
+        } else {
+            Err(StoreError::CannotCastToType)
+        }
+    }
+
+    /// Moves the entity at `row` into `target` with `op` applied, returning the entity moved into
+    /// its place along with its new row in `target`
+    ///
+    /// Fails before moving anything if `row` is out of bounds.
+    pub fn migrate(
+        &self,
+        target: &mut Self,
+        row: usize,
+        op: Migration,
+    ) -> Result<(EntityId, usize), StoreError> {
+        if row >= self.len() {
+            return Err(StoreError::Placeholder);
+        }
+
+        let moved: EntityId = self.get_last_entity().ok_or(StoreError::Placeholder)?;
         let target_row = target.entities().len();
-        let current = self.entities_mut().swap_remove(row);
-        target.entities_mut().push(current);
+        let current = self.swap_remove_entity(row);
+        target.push_entities(&[current]);
         match op {
-            Migration::Add(comp) => {
-                for (&type_id, &idx) in self.index.iter() {
-                    let source_store: &ComponentStore = &self.storage[idx];
-                    let target_store: &ComponentStore = target.get_storage(type_id).unwrap();
-                    source_store.inner_mut().migrate(row, target_store).unwrap();
-                }
-                target
-                    .get_storage(comp.inner_type_id())
-                    .unwrap()
-                    .inner_mut()
-                    .push(comp)
-                    .unwrap();
-            }
             Migration::Remove(type_id) => {
                 for (&type_id, &idx) in target.index.iter() {
-                    let source_store: &ComponentStore = self.get_storage(type_id).unwrap();
-                    let target_store: &ComponentStore = &mut target.storage[idx];
-                    source_store.inner_mut().migrate(row, target_store).unwrap();
+                    let source_store: &ComponentStore = self
+                        .get_storage(type_id)
+                        .ok_or(StoreError::StorageNotFound)?;
+                    let target_store: &ComponentStore = &target.storage[idx];
+                    source_store
+                        .inner_mut()
+                        .migrate(row, &mut *target_store.inner_mut())?;
                 }
                 self.get_storage(type_id)
-                    .unwrap()
+                    .ok_or(StoreError::StorageNotFound)?
                     .inner_mut()
-                    .swap_remove(row);
+                    .swap_remove(row)
+                    .ok_or(StoreError::Placeholder)?;
+            }
+            added => {
+                for (&type_id, &idx) in self.index.iter() {
+                    let source_store: &ComponentStore = &self.storage[idx];
+                    let target_store: &ComponentStore = target
+                        .get_storage(type_id)
+                        .ok_or(StoreError::StorageNotFound)?;
+                    source_store
+                        .inner_mut()
+                        .migrate(row, &mut *target_store.inner_mut())?;
+                }
+                for comp in added.into_added().component_iter() {
+                    target
+                        .get_storage(comp.inner_type_id())
+                        .ok_or(StoreError::StorageNotFound)?
+                        .inner_mut()
+                        .push(comp)?;
+                }
             }
         }
 
-        (moved, target_row)
+        Ok((moved, target_row))
     }
 
-    pub fn migrate_to_bundle(&self, row: usize, op: Migration) -> (EntityId, ComponentBundle) {
-        let mut bundle: ComponentBundle = ComponentBundle::default();
-        for idx in self.index.values() {
-            let comp: ComponentBox = self.storage[*idx].inner_mut().swap_remove(row);
-            bundle.insert_box(comp);
+    /// Moves the entity at `row` into `target`, which must contain every type in this archetype
+    ///
+    /// `fill` provides components for any types only present in `target`. Nothing is moved if
+    /// the archetypes are incompatible or `fill` cannot provide a component.
+    pub fn transfer<F>(
+        &self,
+        target: &Self,
+        row: usize,
+        mut fill: F,
+    ) -> Result<(EntityId, usize), StoreError>
+    where
+        F: FnMut(TypeId) -> Option<ComponentBox>,
+    {
+        if !target.types_ref().is_superset_of(self.types_ref()) {
+            return Err(StoreError::StorageNotFound);
         }
-        match op {
-            Migration::Add(comp) => bundle.insert_box(comp),
-            Migration::Remove(ty) => {
-                bundle.remove(ty).unwrap();
-            }
+        let extras: Vec<ComponentBox> = target
+            .index
+            .keys()
+            .filter(|&&type_id| !self.has_type(type_id))
+            .map(|&type_id| fill(type_id).ok_or(StoreError::TypeNotRegistered))
+            .collect::<Result<_, _>>()?;
+
+        let moved: EntityId = self.get_last_entity().ok_or(StoreError::Placeholder)?;
+        let target_row: usize = target.len();
+        for (&type_id, &idx) in self.index.iter() {
+            let target_store: &ComponentStore = target
+                .get_storage(type_id)
+                .ok_or(StoreError::StorageNotFound)?;
+            self.storage[idx]
+                .inner_mut()
+                .migrate(row, &mut *target_store.inner_mut())?;
+        }
+        for comp in extras.into_iter() {
+            target
+                .get_storage(comp.inner_type_id())
+                .ok_or(StoreError::StorageNotFound)?
+                .inner_mut()
+                .push(comp)?;
+        }
+        let current: EntityId = self.swap_remove_entity(row);
+        target.push_entities(&[current]);
+
+        Ok((moved, target_row))
+    }
+
+    /// Swap-removes the entity at `row` into a `ComponentBundle` with `op` applied
+    ///
+    /// A removed type is dropped rather than collected, and added components are included. Fails
+    /// before removing anything if `row` is out of bounds.
+    pub fn migrate_to_bundle(
+        &self,
+        row: usize,
+        op: Migration,
+    ) -> Result<(EntityId, ComponentBundle), StoreError> {
+        if row >= self.len() {
+            return Err(StoreError::Placeholder);
+        }
+
+        let removed: Option<TypeId> = match &op {
+            Migration::Remove(type_id) => Some(*type_id),
+            _ => None,
         };
-        let entity = self.get_last_entity().unwrap();
-        self.entities_mut().swap_remove(row);
+        let mut bundle: ComponentBundle = ComponentBundle::default();
+        for (type_id, idx) in self.index.iter() {
+            let comp: ComponentBox = self.storage[*idx]
+                .inner_mut()
+                .swap_remove(row)
+                .ok_or(StoreError::Placeholder)?;
+            if removed != Some(*type_id) {
+                bundle.push_box(comp);
+            }
+        }
+        for comp in op.into_added().component_iter() {
+            bundle.push_box(comp);
+        }
+        let entity: EntityId = self.get_last_entity().ok_or(StoreError::Placeholder)?;
+        self.swap_remove_entity(row);
 
-        (entity, bundle)
+        Ok((entity, bundle))
     }
 }
 
@@ -147,27 +484,54 @@ impl Default for Archetype {
     fn default() -> Self {
         Self {
             index: HashMap::new(),
+            types: TypeBundle::default(),
             storage: Box::new([]),
             entities: RwLock::new(Vec::new()),
+            entity_count: AtomicUsize::new(0),
             edges: HashMap::new(),
         }
     }
 }
 
 pub enum Migration {
-    Add(ComponentBox),
+    Add(ComponentBundle),
+    /// Adds a single component, equivalent to `Add` with a one-component bundle
+    Add1(ComponentBox),
     Remove(TypeId),
 }
 
 impl Migration {
     pub fn is_add(&self) -> bool {
-        matches!(self, Self::Add(_))
+        matches!(self, Self::Add(_) | Self::Add1(_))
+    }
+
+    /// Gets the types of the components being added or removed
+    pub fn types(&self) -> TypeBundle {
+        match self {
+            Self::Add(bundle) => bundle.types(),
+            Self::Add1(comp) => TypeBundle::from([comp.inner_type_id()].as_slice()),
+            Self::Remove(type_id) => TypeBundle::from([*type_id].as_slice()),
+        }
+    }
+
+    /// Consumes the migration into the components it adds, which is empty for `Remove`
+    pub fn into_added(self) -> ComponentBundle {
+        match self {
+            Self::Add(bundle) => bundle,
+            Self::Add1(comp) => {
+                let mut bundle: ComponentBundle = ComponentBundle::default();
+                bundle.push_box(comp);
+                bundle
+            }
+            Self::Remove(_) => ComponentBundle::default(),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{entity::EntityStore, test_utils::*};
 
     #[test]
     fn test_get_last_entity() {
@@ -175,4 +539,184 @@ mod tests {
 
         assert!(at.get_last_entity().is_none());
     }
+
+    #[test]
+    fn test_check_migration() {
+        let mut entities = EntityStore::default();
+        let ids: Vec<EntityId> = entities.get_new_ids(2).unwrap();
+        let source = Archetype::new(
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompB::default()),
+            ids[0],
+        );
+        let smaller = Archetype::new(
+            ComponentBundle::default().insert(TestCompA::default()),
+            ids[1],
+        );
+        let added: Migration = Migration::Add1(TestCompC::default().into());
+
+        assert!(source
+            .check_migration(&smaller, &Migration::Remove(TypeId::of::<TestCompB>()))
+            .is_ok());
+        assert!(matches!(
+            source.check_migration(&smaller, &Migration::Remove(TypeId::of::<TestCompA>())),
+            Err(StoreError::CannotCastToType)
+        ));
+        assert!(matches!(
+            smaller.check_migration(&source, &added),
+            Err(StoreError::CannotCastToType)
+        ));
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut entities = EntityStore::default();
+        let at = Archetype::with_capacity(
+            ComponentBundle::default().insert(TestCompA::default()),
+            entities.get_new_id().unwrap(),
+            64,
+        );
+
+        assert!(at.len() == 1);
+        assert!(at.entities().capacity() >= 64);
+        assert!(
+            at.get_storage(TypeId::of::<TestCompA>())
+                .unwrap()
+                .inner()
+                .to_any()
+                .downcast_ref::<Vec<TestCompA>>()
+                .unwrap()
+                .capacity()
+                >= 64
+        );
+    }
+
+    #[test]
+    fn test_edges() {
+        let mut at = Archetype::default();
+        assert!(at.get_edge(TypeId::of::<TestCompA>()).is_none());
+
+        at.set_edge(TypeId::of::<TestCompA>(), 3);
+        assert!(at.get_edge(TypeId::of::<TestCompA>()) == Some(3));
+        assert!(at.get_edge(TypeId::of::<TestCompB>()).is_none());
+    }
+
+    #[test]
+    fn test_types_ref_cached() {
+        let mut entities = EntityStore::default();
+        let id: EntityId = entities.get_new_id().unwrap();
+        let at = Archetype::new(
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompB::default()),
+            id,
+        );
+        let expected: TypeBundle =
+            TypeBundle::from([TypeId::of::<TestCompA>(), TypeId::of::<TestCompB>()].as_slice());
+
+        assert!(*at.types_ref() == expected);
+        assert!(at.types() == expected);
+        assert!(*at.duplicate().types_ref() == expected);
+        assert!(*Archetype::default().types_ref() == TypeBundle::default());
+    }
+
+    #[test]
+    fn test_remove_only_entity() {
+        let mut entities = EntityStore::default();
+        let id: EntityId = entities.get_new_id().unwrap();
+        let at = Archetype::new(ComponentBundle::default().insert(TestCompA::default()), id);
+        assert!(at.get_last_entity() == Some(id));
+
+        let (moved, bundle) = at.remove(0).unwrap();
+
+        assert!(moved == id);
+        assert!(bundle.types() == TypeBundle::from([TypeId::of::<TestCompA>()].as_slice()));
+        assert!(at.is_empty());
+        assert!(at.get_last_entity().is_none());
+        assert!(matches!(at.remove(0), Err(StoreError::Placeholder)));
+    }
+
+    #[test]
+    fn test_get_entities_in_range() {
+        let mut entities = EntityStore::default();
+        let ids: Vec<EntityId> = entities.get_new_ids(5).unwrap();
+        let at = Archetype::new(
+            ComponentBundle::default().insert(TestCompA::default()),
+            ids[0],
+        );
+        for &id in ids[1..].iter() {
+            at.add(ComponentBundle::default().insert(TestCompA::default()), id)
+                .unwrap();
+        }
+
+        assert!(at.get_entities_in_range(1, 3) == ids[1..3]);
+        assert!(at.get_entities_in_range(3, 10) == ids[3..]);
+        assert!(at.get_entities_in_range(7, 10).is_empty());
+    }
+
+    #[test]
+    fn test_entity_count_in_sync() {
+        let ids: Vec<EntityId> = EntityStore::default().get_new_ids(4).unwrap();
+        let bundle = || {
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompB::default())
+        };
+
+        let at = Archetype::new(bundle(), ids[0]);
+        let mut target = Archetype::new(bundle().insert(TestCompC::default()), ids[1]);
+        at.add(bundle(), ids[2]).unwrap();
+        at.add_batch([bundle()], &ids[3..]);
+        assert!(at.entity_count() == 3);
+        debug_assert_eq!(at.entity_count(), at.entities().len());
+
+        at.migrate(
+            &mut target,
+            0,
+            Migration::Add1(ComponentBox::new(TestCompC::default())),
+        )
+        .unwrap();
+        at.remove(0).unwrap();
+        assert!(matches!(at.remove(1), Err(StoreError::Placeholder)));
+        assert!(matches!(
+            at.migrate(&mut target, 1, Migration::Remove(TypeId::of::<TestCompA>())),
+            Err(StoreError::Placeholder)
+        ));
+        assert!(at.entity_count() == 1);
+        assert!(target.entity_count() == 2);
+        debug_assert_eq!(at.entity_count(), at.entities().len());
+        debug_assert_eq!(target.entity_count(), target.entities().len());
+    }
+
+    #[test]
+    fn test_add_type_mismatch() {
+        let ids: Vec<EntityId> = EntityStore::default().get_new_ids(3).unwrap();
+        let at = Archetype::new(
+            ComponentBundle::default().insert(TestCompA::default()),
+            ids[0],
+        );
+
+        let extra = ComponentBundle::default()
+            .insert(TestCompA::default())
+            .insert(TestCompB::default());
+        let missing = ComponentBundle::default().insert(TestCompB::default());
+
+        assert!(matches!(
+            at.add(extra, ids[1]),
+            Err(StoreError::TypeMismatch)
+        ));
+        assert!(matches!(
+            at.add(missing, ids[2]),
+            Err(StoreError::TypeMismatch)
+        ));
+        assert!(at.entity_count() == 1);
+        assert!(
+            at.get_storage(TypeId::of::<TestCompA>())
+                .unwrap()
+                .inner()
+                .len()
+                == 1
+        );
+    }
 }
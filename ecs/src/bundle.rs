@@ -1,14 +1,22 @@
 use std::{
     any::TypeId,
-    collections::{btree_set::Iter, BTreeSet, HashMap},
+    collections::{btree_set, btree_set::Iter, BTreeSet, HashMap},
+    fmt,
+    sync::{Arc, OnceLock, RwLock},
     vec::IntoIter,
 };
 
 use crate::{
-    component::{Component, ComponentBox},
+    component::{Component, ComponentBox, StorageKind},
     errors::StoreError,
 };
 
+static TYPE_NAMES: OnceLock<RwLock<HashMap<TypeId, &'static str>>> = OnceLock::new();
+
+fn type_names() -> &'static RwLock<HashMap<TypeId, &'static str>> {
+    TYPE_NAMES.get_or_init(Default::default)
+}
+
 /// Defines the type identifier for an `Archetype`. all immutable instances are sorted
 ///
 /// Uses a `BTreeSet` to remain hashable
@@ -23,6 +31,11 @@ impl TypeBundle {
         Self(new)
     }
 
+    /// Create a new `TypeBundle` by adding every type in `types`
+    pub fn add_types(&self, types: &Self) -> Self {
+        Self(self.0.union(&types.0).cloned().collect())
+    }
+
     /// Create a new `TypeBundle` by removing the provided `type_id`
     pub fn remove_type(&self, type_id: TypeId) -> Self {
         let mut new: BTreeSet<TypeId> = self.0.clone();
@@ -30,13 +43,75 @@ impl TypeBundle {
         Self(new)
     }
 
-    pub fn contains(&self, bundle: &Self) -> bool {
+    /// Check whether every type in `bundle` is also in this bundle
+    pub fn is_superset_of(&self, bundle: &Self) -> bool {
         self.0.is_superset(&bundle.0)
     }
 
-    pub fn iter(&self) -> Iter<TypeId> {
+    #[deprecated(note = "renamed to `is_superset_of`, see `contains_type` for single types")]
+    pub fn contains(&self, bundle: &Self) -> bool {
+        self.is_superset_of(bundle)
+    }
+
+    /// Check whether `type_id` is in this bundle
+    pub fn contains_type(&self, type_id: TypeId) -> bool {
+        self.0.contains(&type_id)
+    }
+
+    /// Check whether this bundle shares at least one type with `other`
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.0.intersection(&other.0).next().is_some()
+    }
+
+    pub fn iter(&self) -> Iter<'_, TypeId> {
         self.0.iter()
     }
+
+    /// Registers a human-readable name for `T`, used when displaying any `TypeBundle` on any thread
+    ///
+    /// A `World` registers the names of its `Component`s as it first stores each of them.
+    pub fn register_name<T: 'static>(name: &'static str) {
+        type_names()
+            .write()
+            .unwrap()
+            .entry(TypeId::of::<T>())
+            .or_insert(name);
+    }
+}
+
+impl fmt::Display for TypeBundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = type_names().read().unwrap();
+        for (idx, type_id) in self.0.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" + ")?;
+            }
+            match names.get(type_id) {
+                Some(name) => f.write_str(name)?,
+                None => write!(f, "<unknown:{:?}>", type_id)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl IntoIterator for TypeBundle {
+    type Item = TypeId;
+    type IntoIter = btree_set::IntoIter<TypeId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a TypeBundle {
+    type Item = &'a TypeId;
+    type IntoIter = Iter<'a, TypeId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 impl From<&HashMap<TypeId, usize>> for TypeBundle {
@@ -52,45 +127,132 @@ impl From<&[TypeId]> for TypeBundle {
     }
 }
 
+/// Defines a `Bundle`. Any type that can be converted into a `ComponentBundle` for spawning
+///
+/// Implemented for every type with an `Into<ComponentBundle>` conversion.
+pub trait Bundle: Into<ComponentBundle> {}
+
+impl<T: Into<ComponentBundle>> Bundle for T {}
+
 /// Defines a `ComponentBundle`. Stores a collection of unique `Components` associated with the same `Entity`
 ///
 /// Uses a `HashMap` for type associations, storing references to related `Components`
+///
+/// Bundles do not implement `Clone`, as they may hold non-`Clone` components. See `try_clone` for a
+/// fallible copy, or `Prefab` for templates that can always be cloned.
 #[derive(Default)]
 pub struct ComponentBundle {
     index: HashMap<TypeId, usize>,
     components: Vec<ComponentBox>,
 }
 
+/// Creates a bundle holding a single component
+impl<T: Component> From<T> for ComponentBundle {
+    fn from(comp: T) -> Self {
+        Self::default().insert(comp)
+    }
+}
+
 impl ComponentBundle {
-    /// Add a raw `Component` to the bundle
+    /// Add a raw `Component` to the bundle, consuming it for builder chains
+    ///
+    /// Use `push` to add to a bundle in place, e.g. inside loops or conditionals.
     pub fn insert<T: Component>(mut self, comp: T) -> Self {
-        self.index.insert(TypeId::of::<T>(), self.index.len());
-        self.components.push(comp.into());
+        self.push(comp);
         self
     }
 
-    /// Add a `ComponentBox` to the bundle as a non-consuming reference
-    pub fn insert_box(&mut self, comp: ComponentBox) {
+    /// Add a raw `Component` to the bundle in place
+    pub fn push<T: Component>(&mut self, comp: T) {
+        self.push_box(comp.into());
+    }
+
+    /// Add a `ComponentBox` to the bundle in place
+    pub fn push_box(&mut self, comp: ComponentBox) {
         self.index.insert(comp.inner_type_id(), self.index.len());
         self.components.push(comp);
     }
 
+    /// Add a `ComponentBox` to the bundle as a non-consuming reference
+    #[deprecated(note = "renamed to `push_box`, matching `push` for in-place additions")]
+    pub fn insert_box(&mut self, comp: ComponentBox) {
+        self.push_box(comp);
+    }
+
+    /// Merge every `ComponentBox` of `other` into the bundle
+    ///
+    /// Fails without merging anything if both bundles share a type
+    pub fn extend(mut self, other: ComponentBundle) -> Result<Self, StoreError> {
+        self.try_extend(other)?;
+        Ok(self)
+    }
+
+    /// Merge every `ComponentBox` of `other` into the bundle as a non-consuming reference
+    ///
+    /// Fails without merging anything if both bundles share a type
+    pub fn try_extend(&mut self, other: ComponentBundle) -> Result<(), StoreError> {
+        if other
+            .index
+            .keys()
+            .any(|type_id| self.index.contains_key(type_id))
+        {
+            return Err(StoreError::DuplicateType);
+        }
+
+        other.component_iter().for_each(|comp| self.push_box(comp));
+
+        Ok(())
+    }
+
+    /// Clone every contained `Component`, returning `None` if any does not implement `Clone`
+    pub fn try_clone(&self) -> Option<Self> {
+        Some(Self {
+            index: self.index.clone(),
+            components: self
+                .components
+                .iter()
+                .map(|comp| comp.try_clone())
+                .collect::<Option<_>>()?,
+        })
+    }
+
     /// Remove a `ComponentBox` from the bundle matching the given type_id
     pub fn remove(&mut self, type_id: TypeId) -> Result<ComponentBox, StoreError> {
-        let moved: TypeId = self
-            .components
-            .last()
-            .expect("expected bundle to contain a value")
-            .inner_type_id();
         let idx: usize = self
             .index
             .remove(&type_id)
             .ok_or(StoreError::TypeNotFound)?;
+        let removed: ComponentBox = self.components.swap_remove(idx);
 
-        self.index.insert(moved, idx);
-        let moved: ComponentBox = self.components.swap_remove(idx);
+        // the last component only moves into `idx` if it was not the one removed
+        if let Some(moved) = self.components.get(idx) {
+            self.index.insert(moved.inner_type_id(), idx);
+        }
+
+        Ok(removed)
+    }
+
+    /// Remove every `ComponentBox` with `StorageKind::Sparse` from the bundle
+    pub(crate) fn take_sparse(&mut self) -> Vec<ComponentBox> {
+        let sparse: Vec<TypeId> = self
+            .components
+            .iter()
+            .filter(|comp| comp.storage_kind() == StorageKind::Sparse)
+            .map(ComponentBox::inner_type_id)
+            .collect();
 
-        Ok(moved)
+        sparse
+            .into_iter()
+            .map(|type_id| {
+                self.remove(type_id)
+                    .expect("sparse type was found in the bundle")
+            })
+            .collect()
+    }
+
+    /// Remove a `Component` from the bundle by type, casting it back into its native type
+    pub fn remove_typed<T: Component>(&mut self) -> Result<T, StoreError> {
+        self.remove(TypeId::of::<T>())?.cast_inner::<T>()
     }
 
     /// Gets the associated `TypeBundle` for the bundle
@@ -98,17 +260,196 @@ impl ComponentBundle {
         (&self.index).into()
     }
 
+    /// Provides an `Iterator` over references to every contained `ComponentBox`, in insertion order
+    pub fn iter_boxes(&self) -> impl Iterator<Item = &ComponentBox> {
+        self.components.iter()
+    }
+
+    /// Provides an `Iterator` over mutable references to every contained `ComponentBox`
+    pub fn iter_boxes_mut(&mut self) -> impl Iterator<Item = &mut ComponentBox> {
+        self.components.iter_mut()
+    }
+
     /// Consumes the bundle to provide an `Iterator` over every contained `ComponentBox`
     pub fn component_iter(self) -> IntoIter<ComponentBox> {
         self.components.into_iter()
     }
 }
 
+/// Defines a `Prefab`. A template of `Clone` components that can be spawned any number of times
+///
+/// Only `Component + Clone` types can be inserted, so adding any other type fails to build with
+/// ``the trait bound `T: Clone` is not satisfied`` instead of failing when the prefab is cloned.
+#[derive(Clone, Default)]
+pub struct Prefab {
+    components: Vec<Arc<dyn Fn() -> ComponentBox + Send + Sync>>,
+}
+
+impl Prefab {
+    /// Add a `Clone` component to the prefab, consuming it for builder chains
+    pub fn insert<T: Component + Clone>(mut self, comp: T) -> Self {
+        self.push(comp);
+        self
+    }
+
+    /// Add a `Clone` component to the prefab in place
+    pub fn push<T: Component + Clone>(&mut self, comp: T) {
+        self.components
+            .push(Arc::new(move || ComponentBox::new(comp.clone())));
+    }
+}
+
+/// Creates a bundle holding a fresh clone of every component in the prefab
+impl From<&Prefab> for ComponentBundle {
+    fn from(prefab: &Prefab) -> Self {
+        let mut bundle: ComponentBundle = ComponentBundle::default();
+        for component in prefab.components.iter() {
+            bundle.push_box(component());
+        }
+
+        bundle
+    }
+}
+
+impl From<Prefab> for ComponentBundle {
+    fn from(prefab: Prefab) -> Self {
+        Self::from(&prefab)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_utils::*;
 
+    #[test]
+    fn test_type_bundle_display() {
+        TypeBundle::register_name::<u8>("Byte");
+        let named: TypeBundle = TypeBundle::from([TypeId::of::<TestCompA>()].as_slice());
+        let mixed: TypeBundle = named.add_type(TypeId::of::<u8>());
+        let unknown: TypeBundle = TypeBundle::from([TypeId::of::<u16>()].as_slice());
+
+        ComponentBox::new(TestCompA::default()).register_name();
+
+        assert!(named.to_string() == "TestCompA");
+        assert!(mixed.to_string().contains("Byte"));
+        assert!(mixed.to_string().contains("TestCompA"));
+        assert!(mixed.to_string().contains(" + "));
+        assert!(unknown.to_string() == format!("<unknown:{:?}>", TypeId::of::<u16>()));
+        assert!(TypeBundle::default().to_string().is_empty());
+    }
+
+    #[test]
+    fn test_type_bundle_into_iter() {
+        let types: TypeBundle =
+            TypeBundle::from([TypeId::of::<TestCompB>(), TypeId::of::<TestCompA>()].as_slice());
+
+        let borrowed: Vec<&TypeId> = (&types).into_iter().collect();
+        assert!(borrowed == types.iter().collect::<Vec<_>>());
+
+        let mut owned: Vec<TypeId> = types.into_iter().collect();
+        owned.sort();
+        let mut expected: Vec<TypeId> = vec![TypeId::of::<TestCompA>(), TypeId::of::<TestCompB>()];
+        expected.sort();
+        assert!(owned == expected);
+    }
+
+    #[test]
+    fn test_type_bundle_membership() {
+        let a: TypeId = TypeId::of::<TestCompA>();
+        let b: TypeId = TypeId::of::<TestCompB>();
+        let c: TypeId = TypeId::of::<TestCompC>();
+        let ab: TypeBundle = TypeBundle::from([a, b].as_slice());
+        let bc: TypeBundle = TypeBundle::from([b, c].as_slice());
+        let a_only: TypeBundle = TypeBundle::from([a].as_slice());
+        let c_only: TypeBundle = TypeBundle::from([c].as_slice());
+
+        assert!(ab.is_superset_of(&a_only));
+        assert!(!a_only.is_superset_of(&ab));
+        assert!(ab.contains_type(b));
+        assert!(!ab.contains_type(c));
+        assert!(ab.intersects(&bc));
+        assert!(!ab.intersects(&c_only));
+        assert!(!ab.intersects(&TypeBundle::default()));
+    }
+
+    #[test]
+    fn test_component_bundle_from_component() {
+        let bundle: ComponentBundle = TestCompA::default().into();
+
+        assert!(bundle.types() == TypeBundle::from([TypeId::of::<TestCompA>()].as_slice()));
+    }
+
+    #[test]
+    fn test_component_bundle_clone() {
+        let prefab: Prefab = Prefab::default()
+            .insert(TestCompA {
+                one: 4,
+                two: "prefab".to_string(),
+            })
+            .insert(TestCompB::default());
+
+        let mut copy: ComponentBundle = prefab.clone().into();
+        let original: ComponentBundle = (&prefab).into();
+
+        assert!(copy.types() == original.types());
+        assert!(copy.remove_typed::<TestCompA>().unwrap().two == "prefab");
+        assert!(original.try_clone().unwrap().iter_boxes().count() == 2);
+    }
+
+    #[test]
+    fn test_component_bundle_extend() {
+        let other: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompB::default())
+            .insert(TestCompC::default());
+        let mut bundle: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompA::default())
+            .extend(other)
+            .unwrap();
+
+        assert!(
+            bundle.types()
+                == TypeBundle::from(
+                    [
+                        TypeId::of::<TestCompA>(),
+                        TypeId::of::<TestCompB>(),
+                        TypeId::of::<TestCompC>()
+                    ]
+                    .as_slice()
+                )
+        );
+        assert!(bundle.remove_typed::<TestCompC>().is_ok());
+        assert!(bundle.remove_typed::<TestCompA>().is_ok());
+        assert!(bundle.remove_typed::<TestCompB>().is_ok());
+    }
+
+    #[test]
+    fn test_component_bundle_try_extend_duplicate() {
+        let mut bundle: ComponentBundle = ComponentBundle::default().insert(TestCompA::default());
+        let other: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompB::default())
+            .insert(TestCompA::default());
+
+        let result = bundle.try_extend(other);
+
+        assert!(matches!(result, Err(StoreError::DuplicateType)));
+        assert!(bundle.types() == TypeBundle::from([TypeId::of::<TestCompA>()].as_slice()));
+    }
+
+    #[test]
+    fn test_component_bundle_push_in_place() {
+        let mut bundle: ComponentBundle = ComponentBundle::default();
+        bundle.push(TestCompA::default());
+        bundle.push_box(TestCompB::default().into());
+
+        assert!(
+            bundle.types()
+                == TypeBundle::from(
+                    [TypeId::of::<TestCompA>(), TypeId::of::<TestCompB>()].as_slice()
+                )
+        );
+    }
+
     #[test]
     fn test_component_bundle_push() {
         let mut bundle: ComponentBundle = ComponentBundle::default().insert(TestCompA::default());
@@ -151,4 +492,71 @@ mod tests {
                 == TestCompB::default()
         );
     }
+
+    #[test]
+    fn test_component_bundle_remove_last() {
+        let mut bundle: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompA::default())
+            .insert(TestCompB::default());
+
+        bundle.remove(TypeId::of::<TestCompB>()).unwrap();
+        assert!(bundle.types() == TypeBundle::from([TypeId::of::<TestCompA>()].as_slice()));
+
+        bundle.remove(TypeId::of::<TestCompA>()).unwrap();
+        assert!(bundle.types().0.is_empty());
+        assert!(matches!(
+            bundle.remove(TypeId::of::<TestCompA>()),
+            Err(StoreError::TypeNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_component_bundle_remove_typed() {
+        let mut bundle: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompA {
+                one: 1,
+                two: "one".to_string(),
+            })
+            .insert(TestCompB::default());
+
+        let res: Result<TestCompA, StoreError> = bundle.remove_typed::<TestCompA>();
+
+        assert!(res.is_ok());
+        assert!(
+            res.unwrap()
+                == TestCompA {
+                    one: 1,
+                    two: "one".to_string(),
+                }
+        );
+        assert!(bundle.types().0 == BTreeSet::from([TypeId::of::<TestCompB>()]));
+        assert!(matches!(
+            bundle.remove_typed::<TestCompA>().unwrap_err(),
+            StoreError::TypeNotFound
+        ));
+    }
+
+    #[test]
+    fn test_component_bundle_iter_boxes() {
+        let mut bundle: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompB::default())
+            .insert(TestCompA::default())
+            .insert(TestCompC::default());
+
+        let types: Vec<TypeId> = bundle
+            .iter_boxes()
+            .map(|comp| comp.inner_type_id())
+            .collect();
+
+        assert!(
+            types
+                == vec![
+                    TypeId::of::<TestCompB>(),
+                    TypeId::of::<TestCompA>(),
+                    TypeId::of::<TestCompC>(),
+                ]
+        );
+        assert!(bundle.iter_boxes_mut().count() == 3);
+        assert!(bundle.component_iter().count() == 3);
+    }
 }
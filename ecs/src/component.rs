@@ -1,9 +1,11 @@
 use std::{
     any::{Any, TypeId},
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Range},
     sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
-use crate::errors::StoreError;
+use crate::{entity::EntityId, errors::StoreError};
 
 /// Defines a `Component`. Has a predefined memory size and can implement Any
 ///
@@ -13,6 +15,65 @@ pub trait Component: Send + Sync + 'static {
     fn to_any(self: Box<Self>) -> Box<dyn Any>;
     /// Cast a boxed instance of a `Component` into a `ComponentStore` containing itself
     fn to_store(self: Box<Self>) -> ComponentStore;
+    /// Registers the display name of the `Component` with `TypeBundle::register_name`
+    fn register_name()
+    where
+        Self: Sized,
+    {
+    }
+    /// Clone the `Component` into a new `ComponentBox`, returning `None` if it does not implement `Clone`
+    fn clone_box(&self) -> Option<ComponentBox> {
+        None
+    }
+    /// Gets where the `Component` is stored, set with `#[component(storage = "sparse")]`
+    fn storage_kind() -> StorageKind
+    where
+        Self: Sized,
+    {
+        StorageKind::Dense
+    }
+}
+
+/// Defines a `StorageKind`. Selects where a `World` stores every `Component` of a type
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StorageKind {
+    /// Stored in the `ComponentStore`s of each `Archetype`, one row per entity
+    #[default]
+    Dense,
+    /// Stored in a single `SparseSet` per `World`, without splitting entities into new `Archetype`s
+    ///
+    /// Queries can exclude sparse `Component`s with `Without`, but panic when run if they read or
+    /// write one.
+    Sparse,
+}
+
+/// Wraps a `Component` reference so derived impls can pick a cloning strategy at compile time
+///
+/// Method resolution prefers `ProbeClone` when the wrapped type implements `Clone`, falling back
+/// to `ProbeNoClone` through an extra auto-ref otherwise.
+#[doc(hidden)]
+pub struct CloneProbe<'c, T>(pub &'c T);
+
+#[doc(hidden)]
+pub trait ProbeClone {
+    fn probe_clone_box(&self) -> Option<ComponentBox>;
+}
+
+impl<T: Component + Clone> ProbeClone for CloneProbe<'_, T> {
+    fn probe_clone_box(&self) -> Option<ComponentBox> {
+        Some(ComponentBox::new(self.0.clone()))
+    }
+}
+
+#[doc(hidden)]
+pub trait ProbeNoClone {
+    fn probe_clone_box(&self) -> Option<ComponentBox>;
+}
+
+impl<T> ProbeNoClone for &CloneProbe<'_, T> {
+    fn probe_clone_box(&self) -> Option<ComponentBox> {
+        None
+    }
 }
 
 /// Defines a `ComponentBox`. Wraps a `Component, allowing it to be passed as established data`
@@ -21,6 +82,9 @@ pub trait Component: Send + Sync + 'static {
 pub struct ComponentBox {
     component: Box<dyn Component>,
     type_id: TypeId,
+    storage: StorageKind,
+    sparse_store_fn: fn() -> ComponentStore,
+    register_name_fn: fn(),
 }
 
 impl ComponentBox {
@@ -29,6 +93,9 @@ impl ComponentBox {
         Self {
             component: Box::new(comp),
             type_id: TypeId::of::<T>(),
+            storage: T::storage_kind(),
+            sparse_store_fn: || SparseSet::<T>::default().into(),
+            register_name_fn: T::register_name,
         }
     }
 
@@ -48,6 +115,26 @@ impl ComponentBox {
         self.type_id
     }
 
+    /// Get the `StorageKind` of the contained `Component`
+    pub fn storage_kind(&self) -> StorageKind {
+        self.storage
+    }
+
+    /// Creates an empty `ComponentStore` backed by a `SparseSet` of the contained `Component` type
+    pub(crate) fn new_sparse_store(&self) -> ComponentStore {
+        (self.sparse_store_fn)()
+    }
+
+    /// Registers the display name of the contained `Component` type, see `TypeBundle::register_name`
+    pub fn register_name(&self) {
+        (self.register_name_fn)()
+    }
+
+    /// Clone the contained `Component`, returning `None` if it does not implement `Clone`
+    pub fn try_clone(&self) -> Option<Self> {
+        self.component.clone_box()
+    }
+
     /// Consumes the `ComponentBox` to create a `ComponentStore` where the first index is populated by the
     /// inner `Component`
     pub fn create_store(self) -> ComponentStore {
@@ -76,11 +163,38 @@ pub trait ComponentVec {
     /// Pushes a given `ComponentBox` into the next available index of the vec, storing it as a `Component`
     fn push(&mut self, comp: ComponentBox) -> Result<(), StoreError>;
     /// Swap-removes a `Component` from the current row, returning it as a `ComponentBox`
-    fn swap_remove(&mut self, row: usize) -> ComponentBox;
-    /// Migrates the `Component` stored within the target row to the end of the target `ComponentStore`
-    fn migrate(&mut self, row: usize, target: &ComponentStore) -> Result<(), StoreError>;
+    ///
+    /// Returns `None` if the row is out of bounds
+    fn swap_remove(&mut self, row: usize) -> Option<ComponentBox>;
+    /// Migrates the `Component` stored within the target row to the end of the target `ComponentVec`
+    ///
+    /// The caller holds both locks, so a store is never locked twice by the same migration.
+    fn migrate(&mut self, row: usize, target: &mut dyn ComponentVec) -> Result<(), StoreError>;
+    /// Clones the `Component`s within the given rows into owned `ComponentBox`es
+    ///
+    /// Returns `None` if the range is out of bounds or the `Component` cannot be cloned
+    fn clone_range(&self, rows: Range<usize>) -> Option<Vec<ComponentBox>>;
+    /// Reserves space for at least `additional` more `Component`s
+    fn reserve(&mut self, additional: usize);
+    /// Removes every `Component`, returning them as `ComponentBox`es in row order
+    fn drain(&mut self) -> Vec<ComponentBox>;
+    /// Creates a new, empty `ComponentStore` holding the same `Component` type
+    fn to_empty_store(&self) -> ComponentStore;
     fn len(&self) -> usize;
     fn is_empty(&self) -> bool;
+    /// Gets the row holding the `Component` of `entity`, for storage addressed by `EntityId`
+    fn entity_row(&self, _entity: EntityId) -> Option<usize> {
+        None
+    }
+    /// Inserts the `Component` of `entity`, returning the one it replaced, for storage addressed by
+    /// `EntityId`
+    fn insert_for(
+        &mut self,
+        _entity: EntityId,
+        _comp: ComponentBox,
+    ) -> Result<Option<ComponentBox>, StoreError> {
+        Err(StoreError::WrongStorageKind)
+    }
 }
 
 impl<T> ComponentVec for Vec<T>
@@ -100,14 +214,13 @@ where
         Ok(())
     }
 
-    fn swap_remove(&mut self, row: usize) -> ComponentBox {
-        self.swap_remove(row).into()
+    fn swap_remove(&mut self, row: usize) -> Option<ComponentBox> {
+        (row < self.len()).then(|| Vec::swap_remove(self, row).into())
     }
 
-    fn migrate(&mut self, row: usize, target: &ComponentStore) -> Result<(), StoreError> {
+    fn migrate(&mut self, row: usize, target: &mut dyn ComponentVec) -> Result<(), StoreError> {
         let comp: T = self.swap_remove(row);
         target
-            .inner_mut()
             .to_any_mut()
             .downcast_mut::<Vec<T>>()
             .ok_or(StoreError::CannotCastToType)?
@@ -116,6 +229,22 @@ where
         Ok(())
     }
 
+    fn clone_range(&self, rows: Range<usize>) -> Option<Vec<ComponentBox>> {
+        self.get(rows)?.iter().map(Component::clone_box).collect()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+
+    fn drain(&mut self) -> Vec<ComponentBox> {
+        Vec::drain(self, ..).map(ComponentBox::new).collect()
+    }
+
+    fn to_empty_store(&self) -> ComponentStore {
+        Vec::<T>::new().into()
+    }
+
     fn len(&self) -> usize {
         (*self).len()
     }
@@ -125,20 +254,192 @@ where
     }
 }
 
+/// Defines a `SparseSet`. Stores `Component`s densely while addressing them by `EntityId`
+///
+/// Removal swaps the last `Component` into the freed row, so rows are not stable.
+pub struct SparseSet<T> {
+    sparse: Vec<Option<usize>>,
+    dense: Vec<T>,
+    entities: Vec<EntityId>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    /// Inserts the component of `entity`, returning the one previously stored at its index
+    pub fn insert(&mut self, entity: EntityId, comp: T) -> Option<T> {
+        let idx: usize = entity.index() as usize;
+        if let Some(row) = self.sparse.get(idx).copied().flatten() {
+            self.entities[row] = entity;
+            return Some(std::mem::replace(&mut self.dense[row], comp));
+        }
+
+        if idx >= self.sparse.len() {
+            self.sparse.resize(idx + 1, None);
+        }
+        self.sparse[idx] = Some(self.dense.len());
+        self.dense.push(comp);
+        self.entities.push(entity);
+
+        None
+    }
+
+    /// Removes the component of `entity`, if it has one
+    pub fn remove(&mut self, entity: EntityId) -> Option<T> {
+        let row: usize = self.row_of(entity)?;
+        Some(self.swap_remove_row(row))
+    }
+
+    pub fn get(&self, entity: EntityId) -> Option<&T> {
+        Some(&self.dense[self.row_of(entity)?])
+    }
+
+    pub fn get_mut(&mut self, entity: EntityId) -> Option<&mut T> {
+        let row: usize = self.row_of(entity)?;
+        Some(&mut self.dense[row])
+    }
+
+    pub fn contains(&self, entity: EntityId) -> bool {
+        self.row_of(entity).is_some()
+    }
+
+    /// Provides an `Iterator` over every stored component alongside its entity, in row order
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.entities.iter().copied().zip(self.dense.iter())
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.dense
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.dense
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Gets the row of `entity`, rejecting entities from an earlier generation of the same index
+    fn row_of(&self, entity: EntityId) -> Option<usize> {
+        let row: usize = self
+            .sparse
+            .get(entity.index() as usize)
+            .copied()
+            .flatten()?;
+        (self.entities[row] == entity).then_some(row)
+    }
+
+    fn swap_remove_row(&mut self, row: usize) -> T {
+        let removed: EntityId = self.entities.swap_remove(row);
+        self.sparse[removed.index() as usize] = None;
+        if let Some(moved) = self.entities.get(row) {
+            self.sparse[moved.index() as usize] = Some(row);
+        }
+
+        self.dense.swap_remove(row)
+    }
+}
+
+impl<T> ComponentVec for SparseSet<T>
+where
+    T: Component,
+{
+    fn to_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn to_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn push(&mut self, _comp: ComponentBox) -> Result<(), StoreError> {
+        Err(StoreError::WrongStorageKind)
+    }
+
+    fn swap_remove(&mut self, row: usize) -> Option<ComponentBox> {
+        (row < self.len()).then(|| self.swap_remove_row(row).into())
+    }
+
+    fn migrate(&mut self, _row: usize, _target: &mut dyn ComponentVec) -> Result<(), StoreError> {
+        Err(StoreError::WrongStorageKind)
+    }
+
+    fn clone_range(&self, rows: Range<usize>) -> Option<Vec<ComponentBox>> {
+        self.dense
+            .get(rows)?
+            .iter()
+            .map(Component::clone_box)
+            .collect()
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.dense.reserve(additional);
+        self.entities.reserve(additional);
+    }
+
+    fn drain(&mut self) -> Vec<ComponentBox> {
+        self.sparse.clear();
+        self.entities.clear();
+        self.dense.drain(..).map(ComponentBox::new).collect()
+    }
+
+    fn to_empty_store(&self) -> ComponentStore {
+        SparseSet::<T>::default().into()
+    }
+
+    fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    fn entity_row(&self, entity: EntityId) -> Option<usize> {
+        self.row_of(entity)
+    }
+
+    fn insert_for(
+        &mut self,
+        entity: EntityId,
+        comp: ComponentBox,
+    ) -> Result<Option<ComponentBox>, StoreError> {
+        Ok(self
+            .insert(entity, comp.cast_inner::<T>()?)
+            .map(ComponentBox::new))
+    }
+}
+
 /// Defines a `ComponentStore`. Contains and wraps around a `ComponentVec`
+///
+/// Tracks a changed flag for every row, which its `Archetype` keeps aligned with the stored rows.
 pub struct ComponentStore {
     store: Box<RwLock<dyn ComponentVec>>,
     type_id: TypeId,
+    changed: RwLock<Vec<bool>>,
 }
 
 impl ComponentStore {
     /// Fetches a read reference to the inner `ComponentVec`
-    pub fn inner(&self) -> ReadGuard {
+    pub fn inner(&self) -> ReadGuard<'_> {
         self.store.read().unwrap()
     }
 
     /// Fetches a write reference to the inner `ComponentVec`
-    pub fn inner_mut(&self) -> WriteGuard {
+    pub fn inner_mut(&self) -> WriteGuard<'_> {
         self.store.write().unwrap()
     }
 
@@ -146,6 +447,70 @@ impl ComponentStore {
     pub fn inner_type_id(&self) -> TypeId {
         self.type_id
     }
+
+    /// Checks whether both stores hold the same `Component` type and can exchange rows
+    pub fn is_compatible_with(&self, other: &ComponentStore) -> bool {
+        self.type_id == other.type_id
+    }
+
+    /// Gets the number of stored components, holding the read lock only for the call
+    pub fn len(&self) -> usize {
+        self.inner().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner().is_empty()
+    }
+
+    /// Clones the components in rows `start..end`, holding the read lock only for the call
+    ///
+    /// Returns `None` if the range is out of bounds or the component type is not `Clone`
+    pub fn get_range_read(&self, start: usize, end: usize) -> Option<Vec<ComponentBox>> {
+        self.inner().clone_range(start..end)
+    }
+
+    /// Takes every component out of the store in row order, clearing their changed flags
+    pub fn drain(&self) -> Vec<ComponentBox> {
+        self.changed.write().unwrap().clear();
+        self.inner_mut().drain()
+    }
+
+    /// Creates a new, empty `ComponentStore` for the same type as the contained storage
+    pub fn new_empty(&self) -> Self {
+        self.inner().to_empty_store()
+    }
+
+    /// Marks the given rows as changed
+    pub fn mark_changed(&self, rows: &[usize]) {
+        let mut changed = self.changed.write().unwrap();
+        for &row in rows.iter() {
+            changed[row] = true;
+        }
+    }
+
+    /// Marks every row as changed
+    pub fn mark_all_changed(&self) {
+        self.changed.write().unwrap().fill(true);
+    }
+
+    /// Takes the changed flag of every row, resetting them all to unchanged
+    pub fn take_changed(&self) -> Vec<bool> {
+        let mut changed = self.changed.write().unwrap();
+        let len: usize = changed.len();
+        std::mem::replace(&mut *changed, vec![false; len])
+    }
+
+    /// Adds `count` new rows, each flagged as changed
+    pub(crate) fn push_changed(&self, count: usize) {
+        let mut changed = self.changed.write().unwrap();
+        let len: usize = changed.len();
+        changed.resize(len + count, true);
+    }
+
+    /// Swap-removes the changed flag of the given row
+    pub(crate) fn swap_remove_changed(&self, row: usize) {
+        self.changed.write().unwrap().swap_remove(row);
+    }
 }
 
 impl<T: Component> From<T> for ComponentStore {
@@ -153,17 +518,177 @@ impl<T: Component> From<T> for ComponentStore {
         Self {
             store: Box::new(RwLock::new(Vec::<T>::from([value]))),
             type_id: TypeId::of::<T>(),
+            changed: RwLock::new(Vec::from([true])),
         }
     }
 }
 
+/// Creates a store holding every component in the vec, each row flagged as changed
+impl<T: Component> From<Vec<T>> for ComponentStore {
+    fn from(value: Vec<T>) -> Self {
+        let len: usize = value.len();
+        Self {
+            store: Box::new(RwLock::new(value)),
+            type_id: TypeId::of::<T>(),
+            changed: RwLock::new(vec![true; len]),
+        }
+    }
+}
+
+/// Creates a store addressed by `EntityId`, each row flagged as changed
+impl<T: Component> From<SparseSet<T>> for ComponentStore {
+    fn from(value: SparseSet<T>) -> Self {
+        let len: usize = value.len();
+        Self {
+            store: Box::new(RwLock::new(value)),
+            type_id: TypeId::of::<T>(),
+            changed: RwLock::new(vec![true; len]),
+        }
+    }
+}
+
+/// Defines a `ComponentInfo`. Holds type-erased constructors for a registered `Component`
+///
+/// Allows a `World` to build `ComponentBox`es from a bare `TypeId`.
+#[derive(Clone, Copy)]
+pub struct ComponentInfo {
+    clone_fn: fn(&ComponentStore, usize) -> Option<ComponentBox>,
+    default_fn: fn() -> ComponentBox,
+}
+
+impl ComponentInfo {
+    /// Create a new `ComponentInfo` for the given `Component` type
+    pub fn of<T: Component + Clone + Default>() -> Self {
+        Self {
+            clone_fn: |store, row| {
+                column::<T>(&*store.inner())?
+                    .get(row)
+                    .cloned()
+                    .map(ComponentBox::new)
+            },
+            default_fn: || ComponentBox::new(T::default()),
+        }
+    }
+
+    /// Clones the `Component` at the given row of a matching `ComponentStore`
+    pub fn clone_from(&self, store: &ComponentStore, row: usize) -> Option<ComponentBox> {
+        (self.clone_fn)(store, row)
+    }
+
+    /// Creates a default instance of the `Component`
+    pub fn default_box(&self) -> ComponentBox {
+        (self.default_fn)()
+    }
+}
+
+/// Defines a `ComponentRef`. Holds a read lock over a `ComponentStore` while exposing a single row
+///
+/// Dereferences to the `Component` stored at that row.
+pub struct ComponentRef<'s, T> {
+    guard: ReadGuard<'s>,
+    row: usize,
+    marker: PhantomData<T>,
+}
+
+impl<'s, T: Component> ComponentRef<'s, T> {
+    /// Create a new `ComponentRef`, returning `None` if the guard does not hold a `T` at the given row
+    pub fn new(guard: ReadGuard<'s>, row: usize) -> Option<Self> {
+        column::<T>(&*guard)?.get(row)?;
+
+        Some(Self {
+            guard,
+            row,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Component> Deref for ComponentRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &column::<T>(&*self.guard).unwrap()[self.row]
+    }
+}
+
+/// Defines a `ComponentMut`. Holds a write lock over a `ComponentStore` while exposing a single row
+///
+/// Dereferences mutably to the `Component` stored at that row. The row is flagged as changed when
+/// the `ComponentMut` is dropped, if it was dereferenced mutably.
+pub struct ComponentMut<'s, T> {
+    store: &'s ComponentStore,
+    guard: WriteGuard<'s>,
+    row: usize,
+    written: bool,
+    marker: PhantomData<T>,
+}
+
+impl<'s, T: Component> ComponentMut<'s, T> {
+    /// Create a new `ComponentMut`, returning `None` if the store does not hold a `T` at the given row
+    pub fn new(store: &'s ComponentStore, row: usize) -> Option<Self> {
+        let guard: WriteGuard = store.inner_mut();
+        column::<T>(&*guard)?.get(row)?;
+
+        Some(Self {
+            store,
+            guard,
+            row,
+            written: false,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Component> Deref for ComponentMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &column::<T>(&*self.guard).unwrap()[self.row]
+    }
+}
+
+impl<T: Component> DerefMut for ComponentMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.written = true;
+        &mut column_mut::<T>(&mut *self.guard).unwrap()[self.row]
+    }
+}
+
+impl<T> Drop for ComponentMut<'_, T> {
+    fn drop(&mut self) {
+        if self.written {
+            self.store.mark_changed(&[self.row]);
+        }
+    }
+}
+
+/// Gets every `T` of a `ComponentVec` by row, whichever `StorageKind` backs it
+fn column<T: Component>(vec: &dyn ComponentVec) -> Option<&[T]> {
+    let any: &dyn Any = vec.to_any();
+    any.downcast_ref::<Vec<T>>()
+        .map(Vec::as_slice)
+        .or_else(|| any.downcast_ref::<SparseSet<T>>().map(SparseSet::as_slice))
+}
+
+pub(crate) fn column_mut<T: Component>(vec: &mut dyn ComponentVec) -> Option<&mut [T]> {
+    let any: &mut dyn Any = vec.to_any_mut();
+    if any.is::<Vec<T>>() {
+        return any.downcast_mut::<Vec<T>>().map(Vec::as_mut_slice);
+    }
+    any.downcast_mut::<SparseSet<T>>()
+        .map(SparseSet::as_mut_slice)
+}
+
 pub type ReadGuard<'s> = RwLockReadGuard<'s, dyn ComponentVec + 'static>;
 pub type WriteGuard<'s> = RwLockWriteGuard<'s, dyn ComponentVec + 'static>;
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::*;
+    use crate::{
+        entity::{EntityStore, Location},
+        test_utils::*,
+    };
 
     #[test]
     fn test_component_box_cast_inner_fails() {
@@ -196,4 +721,216 @@ mod tests {
                 == TestCompA::default()
         );
     }
+
+    #[test]
+    fn test_component_store_new_empty() {
+        let store: ComponentStore = TestCompA::default().into();
+        let empty: ComponentStore = store.new_empty();
+
+        assert!(empty.inner_type_id() == TypeId::of::<TestCompA>());
+        assert!(empty.inner().is_empty());
+        assert!(store.inner().len() == 1);
+    }
+
+    #[test]
+    fn test_component_store_len() {
+        let store: ComponentStore = TestCompA::default().into();
+        store.inner_mut().push(TestCompA::default().into()).unwrap();
+
+        assert!(store.len() == 2);
+        assert!(!store.is_empty());
+        assert!(store.new_empty().is_empty());
+    }
+
+    #[test]
+    fn test_component_store_get_range_read() {
+        let store: ComponentStore = TestCompA::default().into();
+        for one in 1..4 {
+            store
+                .inner_mut()
+                .push(
+                    TestCompA {
+                        one,
+                        ..Default::default()
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        let range: Vec<ComponentBox> = store.get_range_read(1, 3).unwrap();
+        let ones: Vec<u32> = range
+            .into_iter()
+            .map(|comp| comp.cast_inner::<TestCompA>().unwrap().one)
+            .collect();
+
+        assert!(ones == vec![1, 2]);
+        assert!(store.len() == 4);
+        assert!(store.get_range_read(3, 5).is_none());
+    }
+
+    #[test]
+    fn test_component_vec_swap_remove_out_of_bounds() {
+        let store: ComponentStore = TestCompA::default().into();
+
+        assert!(store.inner_mut().swap_remove(1).is_none());
+        assert!(store.inner_mut().swap_remove(0).is_some());
+        assert!(store.inner_mut().swap_remove(0).is_none());
+    }
+
+    #[test]
+    fn test_component_store_is_compatible_with() {
+        let store: ComponentStore = TestCompA::default().into();
+
+        assert!(store.is_compatible_with(&store.new_empty()));
+        assert!(!store.is_compatible_with(&TestCompB::default().into()));
+    }
+
+    #[test]
+    fn test_component_vec_migrate_between_stores() {
+        let source: ComponentStore = TestCompA {
+            one: 1,
+            ..Default::default()
+        }
+        .into();
+        let target: ComponentStore = source.new_empty();
+
+        let mut source_guard: WriteGuard = source.inner_mut();
+        let mut target_guard: WriteGuard = target.inner_mut();
+        source_guard.migrate(0, &mut *target_guard).unwrap();
+        drop((source_guard, target_guard));
+
+        assert!(source.is_empty());
+        assert!(
+            target
+                .inner()
+                .to_any()
+                .downcast_ref::<Vec<TestCompA>>()
+                .unwrap()[0]
+                == TestCompA {
+                    one: 1,
+                    ..Default::default()
+                }
+        );
+    }
+
+    #[test]
+    fn test_component_vec_migrate_wrong_type() {
+        let source: ComponentStore = TestCompA::default().into();
+        let target: ComponentStore = TestCompB::default().into();
+
+        let res = source.inner_mut().migrate(0, &mut *target.inner_mut());
+
+        assert!(matches!(res, Err(StoreError::CannotCastToType)));
+    }
+
+    #[test]
+    fn test_component_info_clone_from() {
+        let info: ComponentInfo = ComponentInfo::of::<TestCompA>();
+        let store: ComponentStore = TestCompA {
+            one: 1,
+            two: "one".to_string(),
+        }
+        .into();
+
+        let cloned: TestCompA = info.clone_from(&store, 0).unwrap().cast_inner().unwrap();
+
+        assert!(
+            cloned
+                == TestCompA {
+                    one: 1,
+                    two: "one".to_string(),
+                }
+        );
+        assert!(info.clone_from(&store, 1).is_none());
+        assert!(info.default_box().cast_inner::<TestCompA>().unwrap() == TestCompA::default());
+    }
+
+    #[test]
+    fn test_sparse_set_insert_remove() {
+        let mut entities = EntityStore::default();
+        let ids: Vec<EntityId> = entities.get_new_ids(3).unwrap();
+        let mut set: SparseSet<TestCompA> = SparseSet::default();
+
+        for (&id, one) in ids.iter().zip(0..) {
+            assert!(set
+                .insert(
+                    id,
+                    TestCompA {
+                        one,
+                        ..Default::default()
+                    }
+                )
+                .is_none());
+        }
+        assert!(set.remove(ids[0]).unwrap().one == 0);
+
+        assert!(set.len() == 2);
+        assert!(!set.contains(ids[0]));
+        assert!(set.get(ids[1]).unwrap().one == 1);
+        assert!(set.get(ids[2]).unwrap().one == 2);
+        assert!(set.iter().all(|(id, comp)| ids[comp.one as usize] == id));
+    }
+
+    #[test]
+    fn test_sparse_set_rejects_stale_generation() {
+        let mut entities = EntityStore::default();
+        let stale: EntityId = entities.get_new_id().unwrap();
+        entities.set_location(stale, Location::new(0, 0)).unwrap();
+        entities.free(stale).unwrap();
+        let reused: EntityId = entities.get_new_id().unwrap();
+        let mut set: SparseSet<TestCompA> = SparseSet::default();
+
+        set.insert(stale, TestCompA::default());
+
+        assert!(set.get(reused).is_none());
+        assert!(set.remove(reused).is_none());
+        assert!(set.contains(stale));
+    }
+
+    #[test]
+    fn test_sparse_store_addressed_by_entity() {
+        let mut entities = EntityStore::default();
+        let ids: Vec<EntityId> = entities.get_new_ids(2).unwrap();
+        let store: ComponentStore = SparseSet::<TestCompA>::default().into();
+
+        store
+            .inner_mut()
+            .insert_for(ids[1], TestCompA::default().into())
+            .unwrap();
+
+        assert!(store.inner().entity_row(ids[1]) == Some(0));
+        assert!(store.inner().entity_row(ids[0]).is_none());
+        assert!(matches!(
+            store.inner_mut().push(TestCompA::default().into()),
+            Err(StoreError::WrongStorageKind)
+        ));
+        assert!(ComponentRef::<TestCompA>::new(store.inner(), 0).is_some());
+    }
+
+    #[test]
+    fn test_component_store_drain() {
+        let store: ComponentStore = Vec::from([
+            TestCompA {
+                one: 1,
+                ..Default::default()
+            },
+            TestCompA {
+                one: 2,
+                ..Default::default()
+            },
+        ])
+        .into();
+
+        let drained: Vec<u32> = store
+            .drain()
+            .into_iter()
+            .map(|comp| comp.cast_inner::<TestCompA>().unwrap().one)
+            .collect();
+
+        assert!(drained == vec![1, 2]);
+        assert!(store.is_empty());
+        assert!(store.take_changed().is_empty());
+        assert!(store.drain().is_empty());
+    }
 }
@@ -1,4 +1,4 @@
-use std::ops::Range;
+use std::{cmp::Reverse, collections::BinaryHeap, fmt, ops::Range};
 
 use crate::errors::EntityError;
 
@@ -6,17 +6,33 @@ use crate::errors::EntityError;
 ///
 /// `EntityId`s contain identifiers for unique entites, iterating upwards by
 /// generation when freed.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityId {
     id: u32,
     generation: u32,
 }
 
+impl EntityId {
+    /// Get the index of the `EntityId`, shared by every generation of the same slot
+    pub fn index(&self) -> u32 {
+        self.id
+    }
+}
+
+/// Formats as `id:generation`
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.id, self.generation)
+    }
+}
+
 /// Defines a `Location`. Contains information about entity storage location
 ///
 /// `Location`s contain information for an `Entity`'s linked `Archetype` and
 /// its row within its storage.
 #[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     pub archetype: usize,
     pub row: usize,
@@ -33,10 +49,13 @@ impl Location {
 ///
 /// `Entity` structs contain lookup information for finding attached
 /// components within their associated archetypes.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity {
     generation: u32,
     location: Option<Location>,
+    #[cfg(feature = "debug-names")]
+    name: Option<Box<str>>,
 }
 
 impl Entity {
@@ -53,11 +72,13 @@ impl Entity {
 /// Defines an `EntityStore`. Contains a list of `Entity`s in service as well as freed `EntityId`s
 /// for reuse.
 ///
-/// `EntityStore`s track all `EntityId`s and ensures their uniqueness.
-#[derive(Default, Debug)]
+/// `EntityStore`s track all `EntityId`s and ensures their uniqueness. Freed ids are reused
+/// smallest first.
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityStore {
     entities: Vec<Entity>,
-    freed: Vec<u32>,
+    freed: BinaryHeap<Reverse<u32>>,
     count: u32,
 }
 
@@ -74,6 +95,27 @@ impl EntityStore {
             .and(Ok(entity.location))
     }
 
+    /// Gets the number of allocated `EntityId`s that have not been freed
+    pub fn alive_count(&self) -> usize {
+        self.count as usize - self.freed.len()
+    }
+
+    /// Iterates over every located `Entity` by index, yielding its `EntityId` and `Location`
+    pub fn iter_alive(&self) -> impl Iterator<Item = (EntityId, Location)> + '_ {
+        self.entities.iter().enumerate().filter_map(|(id, entity)| {
+            let generation: u32 = entity.generation;
+            entity.location.map(|location| {
+                (
+                    EntityId {
+                        id: id as u32,
+                        generation,
+                    },
+                    location,
+                )
+            })
+        })
+    }
+
     /// Mutably gets an entity matching by both index and generation
     fn get_mut_entity(&mut self, id: EntityId) -> Result<&mut Entity, EntityError> {
         let entity: &mut Entity = self
@@ -84,6 +126,14 @@ impl EntityStore {
         entity.check_generation(id.generation).and(Ok(entity))
     }
 
+    /// Takes the smallest freed id, reissued at the generation it was freed into
+    fn reuse_freed(&mut self) -> Option<EntityId> {
+        let Reverse(id) = self.freed.pop()?;
+        let generation: u32 = self.entities[id as usize].generation;
+
+        Some(EntityId { id, generation })
+    }
+
     /// Allocates new `entity`s into the `entities` collection, returning their ids
     fn seed_new_ids(&mut self, count: u32) -> Result<Range<u32>, EntityError> {
         let old_count: u32 = self.count;
@@ -101,16 +151,15 @@ impl EntityStore {
 
     /// Gets a collection of unique `EntityId`s from a combination of the `freed` list or
     /// by creating new ids as a fallback
+    ///
+    /// Use this when any valid id will do. See `get_new_ids_strict` to only reuse freed ids.
+    /// Fails without consuming any freed ids if the new ids would overflow the id space.
     pub fn get_new_ids(&mut self, count: u32) -> Result<Vec<EntityId>, EntityError> {
         let free_count: u32 = count.min(self.freed.len() as u32);
-        let mut ids: Vec<EntityId> = self
-            .freed
-            .drain(self.freed.len() - free_count as usize..)
-            .map(|id| {
-                let generation = self.entities[id as usize].generation;
-                EntityId { id, generation }
-            })
-            .collect();
+        if self.count.checked_add(count - free_count).is_none() {
+            return Err(EntityError::TooManyEntities);
+        }
+        let mut ids: Vec<EntityId> = (0..free_count).map_while(|_| self.reuse_freed()).collect();
 
         if count > free_count {
             ids.extend(
@@ -123,12 +172,24 @@ impl EntityStore {
         Ok(ids)
     }
 
+    /// Gets a collection of unique `EntityId`s taken only from the `freed` list
+    ///
+    /// Use this when the id space must not grow, e.g. when refilling slots that were just freed.
+    /// Fails without consuming any freed ids if fewer than `count` are available.
+    pub fn get_new_ids_strict(&mut self, count: u32) -> Result<Vec<EntityId>, EntityError> {
+        if self.freed.len() < count as usize {
+            return Err(EntityError::FreedListTooSmall);
+        }
+
+        let ids: Vec<EntityId> = (0..count).map_while(|_| self.reuse_freed()).collect();
+
+        Ok(ids)
+    }
+
     /// Gets a unique `EntityId` from either the `freed` list or by creating a new id as a fallback
     pub fn get_new_id(&mut self) -> Result<EntityId, EntityError> {
-        if let Some(id) = self.freed.pop() {
-            let generation: u32 = self.entities[id as usize].generation;
-
-            Ok(EntityId { id, generation })
+        if let Some(id) = self.reuse_freed() {
+            Ok(id)
         } else if self.count < u32::MAX {
             let id: u32 = self.count;
             self.count += 1;
@@ -148,20 +209,67 @@ impl EntityStore {
         let old_location: Location = entity.location.ok_or(EntityError::AlreadyFreed)?;
         entity.location = None;
         entity.generation += 1;
-        self.freed.push(id.id);
+        #[cfg(feature = "debug-names")]
+        {
+            entity.name = None;
+        }
+        self.freed.push(Reverse(id.id));
 
         Ok(old_location)
     }
 
+    /// Returns an `EntityId` that was allocated but never located to the `freed` list
+    ///
+    /// Use this to roll back a spawn that failed before the entity was placed in an `Archetype`.
+    pub(crate) fn release(&mut self, id: EntityId) -> Result<(), EntityError> {
+        let entity: &mut Entity = self.get_mut_entity(id)?;
+        debug_assert!(
+            entity.location.is_none(),
+            "released entities must be unplaced"
+        );
+        entity.generation += 1;
+        #[cfg(feature = "debug-names")]
+        {
+            entity.name = None;
+        }
+        self.freed.push(Reverse(id.id));
+
+        Ok(())
+    }
+
     /// Updates the inner `Location` for a given `EntityId`
     ///
     /// Returns the freed location, expecting this data to be or have been cleared already in its `Archetype`
-    pub fn set_location(&mut self, id: EntityId, location: Location) -> Option<Location> {
-        let entity: &mut Entity = self.get_mut_entity(id).unwrap();
+    pub fn set_location(
+        &mut self,
+        id: EntityId,
+        location: Location,
+    ) -> Result<Option<Location>, EntityError> {
+        let entity: &mut Entity = self.get_mut_entity(id)?;
         let old_location: Option<Location> = entity.location;
         entity.location = Some(location);
 
-        old_location
+        Ok(old_location)
+    }
+
+    /// Sets a human-readable name for a given `EntityId`, kept until the id is freed
+    #[cfg(feature = "debug-names")]
+    pub fn set_name(&mut self, id: EntityId, name: &str) -> Result<(), EntityError> {
+        self.get_mut_entity(id)?.name = Some(name.into());
+
+        Ok(())
+    }
+
+    /// Gets the name of a given `EntityId`, if one has been set
+    #[cfg(feature = "debug-names")]
+    pub fn get_name(&self, id: EntityId) -> Result<Option<&str>, EntityError> {
+        let entity: &Entity = self
+            .entities
+            .get(id.id as usize)
+            .ok_or(EntityError::NotFound)?;
+        entity.check_generation(id.generation)?;
+
+        Ok(entity.name.as_deref())
     }
 
     /// Updates the locations of continuous `Entities` within an `Archetype`
@@ -183,9 +291,28 @@ mod tests {
         Entity {
             location,
             generation,
+            #[cfg(feature = "debug-names")]
+            name: None,
         }
     }
 
+    #[test]
+    fn test_entity_id_display_and_hash() {
+        let id: EntityId = EntityId {
+            id: 3,
+            generation: 1,
+        };
+        let names: std::collections::HashMap<EntityId, &str> =
+            std::collections::HashMap::from([(id, "three")]);
+
+        assert!(id.to_string() == "3:1");
+        assert!(names.get(&id) == Some(&"three"));
+        assert!(!names.contains_key(&EntityId {
+            id: 3,
+            generation: 2
+        }));
+    }
+
     #[test]
     fn test_get_new_id() -> Result<(), EntityError> {
         let mut store: EntityStore = EntityStore::default();
@@ -207,7 +334,7 @@ mod tests {
     fn test_get_new_id_freed() -> Result<(), EntityError> {
         let mut store: EntityStore = EntityStore {
             entities: Vec::from([mock_entity(1, None)]),
-            freed: Vec::from([0]),
+            freed: BinaryHeap::from([Reverse(0)]),
             count: 1,
         };
 
@@ -224,11 +351,105 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_new_ids_strict() -> Result<(), EntityError> {
+        let mut store: EntityStore = EntityStore {
+            entities: Vec::from([mock_entity(1, None), mock_entity(2, None)]),
+            freed: BinaryHeap::from([Reverse(0), Reverse(1)]),
+            count: 2,
+        };
+
+        let ids: Vec<EntityId> = store.get_new_ids_strict(2)?;
+
+        assert!(ids.len() == 2);
+        assert!(ids.contains(&EntityId {
+            id: 0,
+            generation: 1
+        }));
+        assert!(ids.contains(&EntityId {
+            id: 1,
+            generation: 2
+        }));
+        assert!(store.freed.is_empty());
+        assert!(store.count == 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_new_ids_strict_too_small() {
+        let mut store: EntityStore = EntityStore {
+            entities: Vec::from([mock_entity(1, None)]),
+            freed: BinaryHeap::from([Reverse(0)]),
+            count: 1,
+        };
+
+        let ids = store.get_new_ids_strict(2);
+
+        assert!(matches!(ids, Err(EntityError::FreedListTooSmall)));
+        assert!(store.freed.peek() == Some(&Reverse(0)));
+        assert!(store.count == 1);
+    }
+
+    #[test]
+    fn test_freed_ids_reused_smallest_first() -> Result<(), EntityError> {
+        let mut store: EntityStore = EntityStore::default();
+        let ids: Vec<EntityId> = store.get_new_ids(6)?;
+        store.set_many_location(&ids, Location::new(0, 0));
+
+        for idx in [3, 1, 5] {
+            store.free(ids[idx])?;
+        }
+        let reused: Vec<u32> = (0..3)
+            .map(|_| store.get_new_id().map(|id| id.index()))
+            .collect::<Result<_, _>>()?;
+        assert!(reused == vec![1, 3, 5]);
+
+        for idx in [4, 0, 2] {
+            store.free(ids[idx])?;
+        }
+        let batch: Vec<u32> = store.get_new_ids(4)?.iter().map(EntityId::index).collect();
+        assert!(batch == vec![0, 2, 4, 6]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_new_ids_full_keeps_freed() {
+        let mut store: EntityStore = EntityStore {
+            entities: Vec::from([mock_entity(1, None)]),
+            freed: BinaryHeap::from([Reverse(0)]),
+            count: u32::MAX,
+        };
+
+        let ids = store.get_new_ids(2);
+
+        assert!(matches!(ids, Err(EntityError::TooManyEntities)));
+        assert!(store.freed.peek() == Some(&Reverse(0)));
+    }
+
+    #[test]
+    fn test_release_unplaced() -> Result<(), EntityError> {
+        let mut store: EntityStore = EntityStore::default();
+        let ids: Vec<EntityId> = store.get_new_ids(2)?;
+
+        store.release(ids[1])?;
+
+        assert!(store.alive_count() == 1);
+        assert!(matches!(
+            store.entity_status(ids[1]),
+            Err(EntityError::WrongGen)
+        ));
+        assert!(store.get_new_id()?.index() == 1);
+
+        Ok(())
+    }
+
     #[test]
     fn test_get_new_id_full() -> Result<(), EntityError> {
         let mut store: EntityStore = EntityStore {
             entities: Vec::new(),
-            freed: Vec::new(),
+            freed: BinaryHeap::new(),
             count: u32::MAX,
         };
 
@@ -246,7 +467,7 @@ mod tests {
 
         let mut store: EntityStore = EntityStore {
             entities: Vec::from([mock_entity(0, Some(location))]),
-            freed: Vec::new(),
+            freed: BinaryHeap::new(),
             count: 1,
         };
 
@@ -259,7 +480,7 @@ mod tests {
 
         assert!(free_res.is_ok() && free_res? == location);
 
-        assert!(store.freed[0] == id.id);
+        assert!(store.freed.peek() == Some(&Reverse(id.id)));
         assert!(store.entity_status(id).is_err());
 
         id.generation += 1;
@@ -277,7 +498,7 @@ mod tests {
 
         let mut store: EntityStore = EntityStore {
             entities: Vec::new(),
-            freed: Vec::new(),
+            freed: BinaryHeap::new(),
             count: 0,
         };
 
@@ -296,7 +517,7 @@ mod tests {
 
         let mut store: EntityStore = EntityStore {
             entities: Vec::from([mock_entity(id.generation, None)]),
-            freed: Vec::new(),
+            freed: BinaryHeap::new(),
             count: 1,
         };
 
@@ -312,19 +533,111 @@ mod tests {
 
         let mut store = EntityStore {
             entities: Vec::from([Entity::default()]),
-            freed: Vec::new(),
+            freed: BinaryHeap::new(),
             count: 1,
         };
 
-        let previous: Option<Location> = store.set_location(
+        let previous: Option<Location> = store
+            .set_location(
+                EntityId {
+                    id: 0,
+                    generation: 0,
+                },
+                location,
+            )
+            .unwrap();
+
+        assert!(previous.is_none());
+        assert!(store.entities[0].location == Some(location));
+    }
+
+    #[test]
+    fn test_set_location_wrong_gen() {
+        let mut store = EntityStore {
+            entities: Vec::from([mock_entity(1, Some(Location::new(0, 0)))]),
+            freed: BinaryHeap::new(),
+            count: 1,
+        };
+
+        let result = store.set_location(
             EntityId {
                 id: 0,
                 generation: 0,
             },
-            location,
+            Location::new(1, 0),
         );
 
-        assert!(previous.is_none());
-        assert!(store.entities[0].location == Some(location));
+        assert!(matches!(result, Err(EntityError::WrongGen)));
+        assert!(store.entities[0].location == Some(Location::new(0, 0)));
+    }
+
+    #[test]
+    fn test_iter_alive() {
+        let location = Location::new(1, 0);
+
+        let store = EntityStore {
+            entities: Vec::from([
+                mock_entity(0, Some(location)),
+                mock_entity(1, None),
+                mock_entity(2, Some(location)),
+            ]),
+            freed: BinaryHeap::from([Reverse(1)]),
+            count: 3,
+        };
+
+        let alive: Vec<(EntityId, Location)> = store.iter_alive().collect();
+
+        assert!(
+            alive
+                == vec![
+                    (
+                        EntityId {
+                            id: 0,
+                            generation: 0
+                        },
+                        location
+                    ),
+                    (
+                        EntityId {
+                            id: 2,
+                            generation: 2
+                        },
+                        location
+                    ),
+                ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut store = EntityStore::default();
+        let entity: EntityId = store.get_new_id().unwrap();
+        let location = Location::new(1, 2);
+
+        let json: String = serde_json::to_string(&entity).unwrap();
+        assert!(serde_json::from_str::<EntityId>(&json).unwrap() == entity);
+
+        let json: String = serde_json::to_string(&location).unwrap();
+        assert!(serde_json::from_str::<Location>(&json).unwrap() == location);
+    }
+
+    #[cfg(feature = "debug-names")]
+    #[test]
+    fn test_entity_names() -> Result<(), EntityError> {
+        let mut store: EntityStore = EntityStore::default();
+        let id: EntityId = store.get_new_id()?;
+        assert!(store.get_name(id)?.is_none());
+
+        store.set_name(id, "player")?;
+        store.set_location(id, Location::new(1, 0))?;
+        assert!(store.get_name(id)? == Some("player"));
+
+        store.free(id)?;
+        assert!(matches!(store.get_name(id), Err(EntityError::WrongGen)));
+        let reused: EntityId = store.get_new_id()?;
+        assert!(store.get_name(reused)?.is_none());
+
+        Ok(())
     }
 }
@@ -3,22 +3,65 @@ use std::error::Error;
 
 #[derive(Debug)]
 pub enum EcsError {
-    Placeholder(String),
+    Entity(EntityError),
+    Store(StoreError),
+    ArchetypeNotFound,
+    SameEntity,
+    SystemNotFound,
+    SystemCyclicDependency,
+    DuplicateSystemName,
+    MultipleMatches,
+    ResourceNotFound,
+    #[cfg(feature = "serde")]
+    Serde(serde_json::Error),
 }
 
 impl fmt::Display for EcsError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.pad(match self {
-            Self::Placeholder(message) => message,
-        })
+        match self {
+            Self::Entity(err) => fmt::Display::fmt(err, f),
+            Self::Store(err) => fmt::Display::fmt(err, f),
+            Self::ArchetypeNotFound => f.pad("archetype not found in world"),
+            Self::SameEntity => f.pad("source and target entities are the same"),
+            Self::SystemNotFound => f.pad("system not registered in world"),
+            Self::SystemCyclicDependency => f.pad("system ordering contains a cycle"),
+            Self::DuplicateSystemName => f.pad("a system with this name is already scheduled"),
+            Self::MultipleMatches => f.pad("query matched more than one entity"),
+            Self::ResourceNotFound => f.pad("resource not inserted in world"),
+            #[cfg(feature = "serde")]
+            Self::Serde(err) => fmt::Display::fmt(err, f),
+        }
     }
 }
 
-impl Error for EcsError {}
+impl Error for EcsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Entity(err) => Some(err),
+            Self::Store(err) => Some(err),
+            #[cfg(feature = "serde")]
+            Self::Serde(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl From<EntityError> for EcsError {
     fn from(err: EntityError) -> Self {
-        Self::Placeholder(err.to_string())
+        Self::Entity(err)
+    }
+}
+
+impl From<StoreError> for EcsError {
+    fn from(err: StoreError) -> Self {
+        Self::Store(err)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for EcsError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Serde(err)
     }
 }
 
@@ -27,6 +70,10 @@ pub enum StoreError {
     CannotCastToType,
     TypeNotFound,
     StorageNotFound,
+    TypeNotRegistered,
+    TypeMismatch,
+    DuplicateType,
+    WrongStorageKind,
     Placeholder,
 }
 
@@ -36,6 +83,10 @@ impl fmt::Display for StoreError {
             Self::CannotCastToType => "cannot cast to specified type",
             Self::TypeNotFound => "the target type could not be found",
             Self::StorageNotFound => "storage not contained in archetype",
+            Self::TypeNotRegistered => "the target type has not been registered",
+            Self::TypeMismatch => "bundle types do not match the target archetype",
+            Self::DuplicateType => "the type is already present in the bundle",
+            Self::WrongStorageKind => "the storage does not match the storage kind of the type",
             Self::Placeholder => "placeholder",
         })
     }
@@ -0,0 +1,31 @@
+use std::any::Any;
+
+use crate::{
+    bundle::TypeBundle,
+    component::{Component, ComponentBox, ComponentStore},
+    entity::EntityId,
+};
+
+/// Defines `Children`. Lists the child `Entity`s owned by the attached `Entity`
+///
+/// Child ids are not validated on insertion, and may refer to entities that have since been freed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Children(pub Vec<EntityId>);
+
+impl Component for Children {
+    fn to_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn to_store(self: Box<Self>) -> ComponentStore {
+        (*self).into()
+    }
+
+    fn register_name() {
+        TypeBundle::register_name::<Self>("Children");
+    }
+
+    fn clone_box(&self) -> Option<ComponentBox> {
+        Some(ComponentBox::new(self.clone()))
+    }
+}
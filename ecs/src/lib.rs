@@ -3,7 +3,10 @@ pub mod bundle;
 pub mod component;
 pub mod entity;
 pub mod errors;
+pub mod hierarchy;
 pub mod query;
+#[cfg(feature = "serde")]
+pub mod snapshot;
 pub mod world;
 
 #[cfg(test)]
@@ -1,64 +1,190 @@
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
 use crate::{
     archetype::Archetype,
     bundle::TypeBundle,
-    component::{ReadGuard, WriteGuard},
+    component::{Component, ComponentBox, ReadGuard, WriteGuard},
+    errors::StoreError,
 };
 
+/// Defines a `QueryModel`. A query whose rows borrow their components from each matching `Archetype`
+///
+/// Implemented with `#[derive(QueryModel)]`, which lists the matched types on the generated impl.
 pub trait QueryModel {
     type Row<'r>;
     fn get_types() -> TypeBundle;
-    fn get_reads(at: &Archetype) -> Vec<ReadGuard>;
-    fn get_writes(at: &Archetype) -> Vec<WriteGuard>;
-    fn process<F>(reads: Vec<ReadGuard>, writes: Vec<WriteGuard>, system: &mut F)
+    /// Gets the types which exclude an archetype from the query
+    fn get_excluded() -> TypeBundle {
+        TypeBundle::default()
+    }
+    /// Gets the types which must have changed for a row to be processed
+    fn get_changed_types() -> TypeBundle {
+        TypeBundle::default()
+    }
+    /// Gets the types the query writes to, flagging processed rows as changed
+    fn get_write_types() -> TypeBundle {
+        TypeBundle::default()
+    }
+    /// Gets the read and write types which use sparse storage, and so can never be fetched
+    fn get_sparse_types() -> TypeBundle {
+        TypeBundle::default()
+    }
+    /// Takes the changed flags of the archetype for `get_changed_types`, resetting them
+    ///
+    /// Returns `None` if the query has no changed filter
+    fn get_changed(at: &Archetype) -> Option<Vec<bool>> {
+        let types: TypeBundle = Self::get_changed_types();
+
+        (types != TypeBundle::default()).then(|| at.take_changed(&types))
+    }
+    fn get_reads(at: &Archetype) -> Vec<ReadGuard<'_>>;
+    fn get_writes(at: &Archetype) -> Vec<WriteGuard<'_>>;
+    /// Processes every row of the archetype, returning the number of rows processed
+    fn process<F>(reads: Vec<ReadGuard>, writes: Vec<WriteGuard>, system: &mut F) -> usize
     where
         for<'m> F: FnMut(Self::Row<'m>);
+    /// Processes only the given rows of the archetype, in the order provided, returning the number
+    /// of rows processed
+    fn process_rows<F>(
+        reads: Vec<ReadGuard>,
+        writes: Vec<WriteGuard>,
+        rows: &[usize],
+        system: &mut F,
+    ) -> usize
+    where
+        for<'m> F: FnMut(Self::Row<'m>);
+    /// Processes every row of the archetype in chunks of `chunk_size` rows, returning the number of
+    /// rows processed
+    ///
+    /// Every storage is split at the same rows, so no two chunks touch the same row. Chunks run in
+    /// parallel when the `parallel` feature is enabled.
+    fn process_parallel<F>(
+        reads: Vec<ReadGuard>,
+        writes: Vec<WriteGuard>,
+        chunk_size: usize,
+        system: &F,
+    ) -> usize
+    where
+        for<'m> F: Fn(Self::Row<'m>) + Send + Sync;
+}
+
+/// Runs `f` over every chunk, on the rayon thread pool when the `parallel` feature is enabled
+#[doc(hidden)]
+pub fn for_each_chunk<C, F>(chunks: Vec<C>, f: F)
+where
+    C: Send,
+    F: Fn(C) + Send + Sync,
+{
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+        chunks.into_par_iter().for_each(f);
+    }
+    #[cfg(not(feature = "parallel"))]
+    chunks.into_iter().for_each(f);
+}
+
+/// Defines an `OwnedQueryModel`. A query whose rows own their components, moved out of storage
+///
+/// Implemented for tuples of up to six distinct `Component` types, see `World::take_components`.
+pub trait OwnedQueryModel: Sized {
+    fn get_types() -> TypeBundle;
+    /// Builds a row from exactly the components of `get_types`, in any order
+    fn from_components(comps: Vec<ComponentBox>) -> Result<Self, StoreError>;
 }
 
-/* EXAMPLE IMPL
+macro_rules! impl_owned_query_model {
+    ($($comp:ident),+) => {
+        impl<$($comp: Component),+> OwnedQueryModel for ($($comp,)+) {
+            fn get_types() -> TypeBundle {
+                TypeBundle::from([$(TypeId::of::<$comp>()),+].as_slice())
+            }
 
-impl Model for TestDataA<'_> {
-    type Row<'r> = TestDataA<'r>;
+            #[allow(non_snake_case)]
+            fn from_components(comps: Vec<ComponentBox>) -> Result<Self, StoreError> {
+                $(let mut $comp: Option<$comp> = None;)+
+                for comp in comps {
+                    let type_id: TypeId = comp.inner_type_id();
+                    $(
+                        if type_id == TypeId::of::<$comp>() {
+                            $comp = Some(comp.cast_inner::<$comp>()?);
+                            continue;
+                        }
+                    )+
+                    return Err(StoreError::TypeNotFound);
+                }
 
-    fn get_types() -> TypeBundle {
-        TypeBundle::from([TypeId::of::<TestCompA>(), TypeId::of::<TestCompB>()].as_slice())
+                Ok(($($comp.ok_or(StoreError::TypeNotFound)?,)+))
+            }
+        }
+    };
+}
+
+impl_owned_query_model!(A);
+impl_owned_query_model!(A, B);
+impl_owned_query_model!(A, B, C);
+impl_owned_query_model!(A, B, C, D);
+impl_owned_query_model!(A, B, C, D, E);
+impl_owned_query_model!(A, B, C, D, E, F);
+
+/// Defines a `Without` marker. Excludes archetypes containing `T` when used as a `QueryModel` field
+pub struct Without<T: Component>(PhantomData<fn() -> T>);
+
+impl<T: Component> Without<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
     }
+}
 
-    fn get_reads(at: &Archetype) -> Vec<ReadGuard> {
-        vec![at.get_storage(TypeId::of::<TestCompA>()).unwrap().inner()]
+impl<T: Component> Default for Without<T> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn get_writes(at: &Archetype) -> Vec<WriteGuard> {
-        vec![at
-            .get_storage(TypeId::of::<TestCompB>())
-            .unwrap()
-            .inner_mut()]
+/// Defines a `Changed` marker. Wraps a `QueryModel` reference field to only yield rows where the
+/// component changed since the last system run
+///
+/// A row is changed when it is added to an archetype or written by a system. Writes through a
+/// `Changed` field do not flag the row again, so a system does not retrigger itself.
+///
+/// Each component store keeps a single changed flag per row, and the first query that filters
+/// on it clears it. Only one system should consume `Changed` for a given component type: any
+/// later system filtering `Changed` on that type sees no rows until they change again.
+pub struct Changed<R>(R);
+
+impl<R> Changed<R> {
+    pub fn new(inner: R) -> Self {
+        Self(inner)
     }
 
-    fn process<F>(
-        reads: Vec<ReadGuard>,
-        mut writes: Vec<WriteGuard>,
-        row: usize,
-        system: &mut F,
-    ) where
-        for<'a> F: FnMut(Self::Row<'a>),
-    {
-        let comp_a: &TestCompA = reads[0]
-            .to_any()
-            .downcast_ref::<Vec<TestCompA>>()
-            .unwrap()
-            .get(row)
-            .unwrap();
-
-        let comp_b: &mut TestCompB = writes[0]
-            .to_any_mut()
-            .downcast_mut::<Vec<TestCompB>>()
-            .unwrap()
-            .get_mut(row)
-            .unwrap();
-
-        let row: Self::Row<'_> = TestDataA { comp_a, comp_b };
-
-        system(row);
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+}
+
+impl<T> Deref for Changed<&T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T> Deref for Changed<&mut T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T> DerefMut for Changed<&mut T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0
     }
 }
-*/
@@ -0,0 +1,79 @@
+use std::{
+    any::{self, Any},
+    collections::HashMap,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    component::{Component, ComponentStore},
+    entity::{EntityId, EntityStore},
+};
+
+type BoxedResource = Box<dyn Any + Send + Sync>;
+
+/// Defines a `WorldSnapshot`. A serializable copy of every archetype, entity, and resource
+///
+/// Components and resources are keyed by type name, so a snapshot can only be restored by a build
+/// registering the same types.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorldSnapshot {
+    pub(crate) entities: EntityStore,
+    pub(crate) archetypes: Vec<ArchetypeSnapshot>,
+    pub(crate) resources: HashMap<String, Value>,
+}
+
+/// Defines an `ArchetypeSnapshot`. The entities of an archetype with one serialized store per type
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct ArchetypeSnapshot {
+    pub(crate) entities: Vec<EntityId>,
+    pub(crate) components: HashMap<String, Value>,
+}
+
+/// Defines a `ComponentSerde`. Holds type-erased serializers for a registered `Component`
+pub(crate) struct ComponentSerde {
+    pub(crate) name: &'static str,
+    pub(crate) serialize: fn(&ComponentStore) -> Result<Value, serde_json::Error>,
+    pub(crate) deserialize: fn(Value) -> Result<ComponentStore, serde_json::Error>,
+}
+
+impl ComponentSerde {
+    pub(crate) fn of<T: Component + Serialize + DeserializeOwned>() -> Self {
+        Self {
+            name: any::type_name::<T>(),
+            serialize: |store: &ComponentStore| {
+                let inner = store.inner();
+                let comps: &Vec<T> = inner
+                    .to_any()
+                    .downcast_ref::<Vec<T>>()
+                    .expect("store type matches its registration");
+                serde_json::to_value(comps)
+            },
+            deserialize: |value: Value| Ok(serde_json::from_value::<Vec<T>>(value)?.into()),
+        }
+    }
+}
+
+/// Defines a `ResourceSerde`. Holds type-erased serializers for a registered resource
+pub(crate) struct ResourceSerde {
+    pub(crate) name: &'static str,
+    pub(crate) serialize: fn(&(dyn Any + Send + Sync)) -> Result<Value, serde_json::Error>,
+    pub(crate) deserialize: fn(Value) -> Result<BoxedResource, serde_json::Error>,
+}
+
+impl ResourceSerde {
+    pub(crate) fn of<T: Serialize + DeserializeOwned + Send + Sync + 'static>() -> Self {
+        Self {
+            name: any::type_name::<T>(),
+            serialize: |resource: &(dyn Any + Send + Sync)| {
+                serde_json::to_value(
+                    resource
+                        .downcast_ref::<T>()
+                        .expect("resource type matches its registration"),
+                )
+            },
+            deserialize: |value: Value| Ok(Box::new(serde_json::from_value::<T>(value)?)),
+        }
+    }
+}
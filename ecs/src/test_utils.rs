@@ -1,19 +1,21 @@
+#![allow(dead_code)]
+
 use crate as ecs;
 use ecs_derive::Component;
 
-#[derive(Component, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug, Clone)]
 pub struct TestCompA {
     pub one: u32,
     pub two: String,
 }
 
-#[derive(Component, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug, Clone)]
 pub struct TestCompB {
     pub three: f32,
     pub four: String,
 }
 
-#[derive(Component, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug, Clone)]
 pub struct TestCompC {
     pub five: Vec<usize>,
     pub six: String,
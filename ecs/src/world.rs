@@ -1,48 +1,333 @@
-use std::{any::TypeId, collections::HashMap};
+use std::{
+    any::{Any, TypeId},
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    ops::Range,
+    time::{Duration, Instant},
+};
 
+#[cfg(feature = "serde")]
+use crate::snapshot::{ArchetypeSnapshot, ComponentSerde, ResourceSerde, WorldSnapshot};
 use crate::{
     archetype::{Archetype, Migration},
-    bundle::{ComponentBundle, TypeBundle},
-    component::{ReadGuard, WriteGuard},
+    bundle::{Bundle, ComponentBundle, TypeBundle},
+    component::{
+        column_mut, Component, ComponentBox, ComponentInfo, ComponentMut, ComponentRef,
+        ComponentStore, ReadGuard, StorageKind, WriteGuard,
+    },
     entity::{EntityId, EntityStore, Location},
-    errors::{EcsError, EntityError},
-    query::QueryModel,
+    errors::{EcsError, EntityError, StoreError},
+    hierarchy::Children,
+    query::{OwnedQueryModel, QueryModel},
 };
 
+/// Identifies a system registered to a `World`
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct SystemId(usize);
+
+type BoxedSystem = Box<dyn FnMut(&mut World)>;
+
+/// Defines a `WorldCommand`. A structural change deferred until after a system has run
+///
+/// Systems cannot spawn, kill, or migrate entities while iterating, so these are queued and applied
+/// with `World::flush_commands`.
+pub enum WorldCommand {
+    Spawn(ComponentBundle),
+    Kill(EntityId),
+    Migrate(EntityId, Migration),
+}
+
+/// Rows per chunk used by `World::run_system_parallel`
+#[cfg(feature = "parallel")]
+pub const PARALLEL_CHUNK_SIZE: usize = 64;
+
 pub struct World {
     index: HashMap<TypeBundle, usize>,
     archetypes: Vec<Archetype>,
     entities: EntityStore,
     inclusive_index: HashMap<TypeBundle, Vec<usize>>,
+    archetypes_by_type: HashMap<TypeId, Vec<usize>>,
+    queries_by_type: HashMap<TypeId, Vec<TypeBundle>>,
+    systems: Vec<BoxedSystem>,
+    system_order: Vec<(SystemId, SystemId)>,
+    registry: HashMap<TypeId, ComponentInfo>,
+    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    events: HashMap<TypeId, Box<dyn AnyEventQueue>>,
+    sparse: HashMap<TypeId, ComponentStore>,
+    archetype_capacity: usize,
+    #[cfg(feature = "serde")]
+    component_serde: HashMap<TypeId, ComponentSerde>,
+    #[cfg(feature = "serde")]
+    resource_serde: HashMap<TypeId, ResourceSerde>,
 }
 
 impl World {
     pub fn init() -> Self {
+        Self::init_with_capacity(0)
+    }
+
+    /// Creates an empty world whose new archetypes each reserve room for `initial_archetype_capacity`
+    /// entities
+    pub fn init_with_capacity(initial_archetype_capacity: usize) -> Self {
         let default_archetype: Archetype = Archetype::default();
         Self {
             index: HashMap::from([(TypeBundle::default(), 0)]),
             archetypes: Vec::from([default_archetype]),
             entities: EntityStore::default(),
             inclusive_index: HashMap::new(),
+            archetypes_by_type: HashMap::new(),
+            queries_by_type: HashMap::new(),
+            systems: Vec::new(),
+            system_order: Vec::new(),
+            registry: HashMap::new(),
+            resources: HashMap::new(),
+            events: HashMap::new(),
+            sparse: HashMap::new(),
+            archetype_capacity: initial_archetype_capacity,
+            #[cfg(feature = "serde")]
+            component_serde: HashMap::new(),
+            #[cfg(feature = "serde")]
+            resource_serde: HashMap::new(),
         }
     }
 
     pub fn spawn(&mut self, bundle: ComponentBundle) -> Result<EntityId, EcsError> {
         let entity: EntityId = self.entities.get_new_id()?;
+        if let Err(err) = self.place_entity(entity, bundle) {
+            self.discard_unplaced(&[entity]);
+            return Err(err);
+        }
+
+        Ok(entity)
+    }
+
+    /// Adds `bundle` as a row of the archetype matching its types, creating the archetype if
+    /// needed, and points `entity` at the new row
+    ///
+    /// Sparse components are moved into their `SparseSet` instead.
+    fn place_entity(
+        &mut self,
+        entity: EntityId,
+        mut bundle: ComponentBundle,
+    ) -> Result<(), EcsError> {
+        for comp in bundle.take_sparse() {
+            self.insert_sparse(entity, comp)?;
+        }
         let types: TypeBundle = bundle.types();
 
         let location: Location = if let Some(archetype_id) = self.get_archetype_id(&types) {
             Location::new(
                 archetype_id,
-                self.archetypes[archetype_id].add(bundle, entity),
+                self.archetypes[archetype_id].add(bundle, entity)?,
             )
         } else {
             Location::new(self.push_archetype(bundle, entity), 0)
         };
 
-        self.entities.set_location(entity, location);
+        self.entities.set_location(entity, location)?;
 
-        Ok(entity)
+        Ok(())
+    }
+
+    /// Spawns a new entity holding only `comp`
+    pub fn spawn_one<T: Component>(&mut self, comp: T) -> Result<EntityId, EcsError> {
+        self.spawn(comp.into())
+    }
+
+    /// Spawns many entities sharing the same types in a single pass over their archetype
+    ///
+    /// Fails without spawning anything if the bundles do not all share the same types, or if any
+    /// sparse component cannot be stored.
+    pub fn spawn_batch(
+        &mut self,
+        mut bundles: Vec<ComponentBundle>,
+    ) -> Result<Vec<EntityId>, EcsError> {
+        let Some(types) = bundles.first().map(|bundle| bundle.types()) else {
+            return Ok(Vec::new());
+        };
+        if bundles.iter().any(|bundle| bundle.types() != types) {
+            return Err(StoreError::TypeMismatch.into());
+        }
+
+        let count: u32 = u32::try_from(bundles.len()).map_err(|_| EntityError::TooManyEntities)?;
+        let entities: Vec<EntityId> = self.entities.get_new_ids(count)?;
+        for (bundle, &entity) in bundles.iter_mut().zip(entities.iter()) {
+            for comp in bundle.take_sparse() {
+                if let Err(err) = self.insert_sparse(entity, comp) {
+                    self.discard_unplaced(&entities);
+                    return Err(err.into());
+                }
+            }
+        }
+        let types: TypeBundle = bundles[0].types();
+        let mut bundles = bundles.into_iter();
+        let start: Location = if let Some(archetype_id) = self.get_archetype_id(&types) {
+            Location::new(
+                archetype_id,
+                self.archetypes[archetype_id].add_batch(bundles, &entities),
+            )
+        } else {
+            let first: ComponentBundle = bundles.next().unwrap();
+            let archetype_id: usize = self.push_archetype(first, entities[0]);
+            self.archetypes[archetype_id].add_batch(bundles, &entities[1..]);
+            Location::new(archetype_id, 0)
+        };
+
+        self.entities.set_many_location(&entities, start);
+
+        Ok(entities)
+    }
+
+    /// Rolls back a spawn that failed before `entities` were placed, removing any sparse
+    /// components already stored for them and returning the ids to the `freed` list
+    fn discard_unplaced(&mut self, entities: &[EntityId]) {
+        let sparse: Vec<TypeId> = self.sparse.keys().copied().collect();
+        for &entity in entities {
+            for &type_id in sparse.iter() {
+                self.remove_sparse(entity, type_id);
+            }
+            self.entities
+                .release(entity)
+                .expect("unplaced entities were just allocated");
+        }
+    }
+
+    /// Spawns `count` entities, each from a clone of `template`
+    ///
+    /// A `Prefab` serves as the template for component sets assembled at runtime.
+    pub fn spawn_n<B: Bundle + Clone>(
+        &mut self,
+        count: usize,
+        template: B,
+    ) -> Result<Vec<EntityId>, EcsError> {
+        self.spawn_batch((0..count).map(|_| template.clone().into()).collect())
+    }
+
+    /// Spawns a new entity from the given bundle, returning its id alongside a clone of its `T` component
+    pub fn spawn_and_get<T: Component + Clone>(
+        &mut self,
+        bundle: ComponentBundle,
+    ) -> Result<(EntityId, T), EcsError> {
+        let type_id: TypeId = TypeId::of::<T>();
+        if !bundle.types().contains_type(type_id) {
+            return Err(StoreError::TypeNotFound.into());
+        }
+
+        let entity: EntityId = self.spawn(bundle)?;
+        let value: T = self
+            .get_component::<T>(entity)
+            .map(|comp| comp.clone())
+            .ok_or(StoreError::CannotCastToType)?;
+
+        Ok((entity, value))
+    }
+
+    /// Registers a `Component` type, allowing it to be cloned or defaulted from its `TypeId`
+    pub fn register_component<T: Component + Clone + Default>(&mut self) {
+        T::register_name();
+        self.registry
+            .insert(TypeId::of::<T>(), ComponentInfo::of::<T>());
+    }
+
+    /// Inserts a resource, a global value not attached to any entity, replacing any previous `T`
+    pub fn insert_resource<T: Send + Sync + 'static>(&mut self, resource: T) {
+        self.resources.insert(TypeId::of::<T>(), Box::new(resource));
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.resources.get(&TypeId::of::<T>())?.downcast_ref::<T>()
+    }
+
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut::<T>()
+    }
+
+    /// Removes a resource, returning it if it was present
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources
+            .remove(&TypeId::of::<T>())?
+            .downcast::<T>()
+            .ok()
+            .map(|resource| *resource)
+    }
+
+    /// Sends an event to every reader of `T` during this frame and the next
+    pub fn send_event<T: Send + Sync + 'static>(&mut self, event: T) {
+        self.events
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<EventQueue<T>>::default())
+            .as_any_mut()
+            .downcast_mut::<EventQueue<T>>()
+            .expect("event queue type matches its key")
+            .events
+            .push(event);
+    }
+
+    /// Reads every `T` event sent during the previous frame and this one, oldest first
+    pub fn read_events<T: 'static>(&self) -> &[T] {
+        self.events
+            .get(&TypeId::of::<T>())
+            .and_then(|queue| queue.as_any().downcast_ref::<EventQueue<T>>())
+            .map_or(&[], |queue| queue.events.as_slice())
+    }
+
+    /// Drops every pending `T` event, regardless of the frame it was sent in
+    pub fn clear_events<T: 'static>(&mut self) {
+        if let Some(queue) = self.events.get_mut(&TypeId::of::<T>()) {
+            queue.clear();
+        }
+    }
+
+    /// Marks a frame boundary, dropping every event sent before the frame that just ended
+    ///
+    /// Events therefore stay readable for one full frame after being sent, whichever order
+    /// systems run in.
+    pub fn next_frame(&mut self) {
+        self.events
+            .values_mut()
+            .for_each(|queue| queue.next_frame());
+    }
+
+    /// Spawns a copy of `source` with exactly the components in `new_types`
+    ///
+    /// Shared components are cloned, components missing from `source` are defaulted, and any
+    /// others are dropped. Every type in `new_types` must be registered.
+    pub fn copy_entity_to_archetype(
+        &mut self,
+        source: EntityId,
+        new_types: TypeBundle,
+    ) -> Result<EntityId, EcsError> {
+        let location: Location = self
+            .entities
+            .entity_status(source)?
+            .ok_or(EntityError::NotFound)?;
+        let archetype: &Archetype = &self.archetypes[location.archetype];
+
+        let mut bundle: ComponentBundle = ComponentBundle::default();
+        for type_id in new_types.iter() {
+            let info: &ComponentInfo = self
+                .registry
+                .get(type_id)
+                .ok_or(StoreError::TypeNotRegistered)?;
+            let stored: Option<(&ComponentStore, usize)> = match self.sparse.get(type_id) {
+                Some(store) => store.inner().entity_row(source).map(|row| (store, row)),
+                None => archetype
+                    .get_storage(*type_id)
+                    .map(|store| (store, location.row)),
+            };
+            let comp: ComponentBox = match stored {
+                Some((store, row)) => info
+                    .clone_from(store, row)
+                    .ok_or(StoreError::CannotCastToType)?,
+                None => info.default_box(),
+            };
+            bundle.push_box(comp);
+        }
+
+        self.spawn(bundle)
     }
 
     pub fn migrate(&mut self, entity: EntityId, op: Migration) -> Result<(), EcsError> {
@@ -50,134 +335,2142 @@ impl World {
             .entities
             .entity_status(entity)?
             .ok_or(EntityError::NotFound)?;
+        let Some(op) = self.migrate_sparse(entity, op)? else {
+            return Ok(());
+        };
         let source_idx: usize = location.archetype;
-        let new_type: TypeId = match &op {
-            Migration::Add(comp) => {
-                let add: TypeId = comp.inner_type_id();
-                assert!(!self.archetypes[source_idx].has_type(add));
-                add
-            }
-            Migration::Remove(type_id) => {
-                assert!(self.archetypes[source_idx].has_type(*type_id));
-                *type_id
-            }
+        let changed: TypeBundle = op.types();
+        match &op {
+            Migration::Remove(type_id) => assert!(self.archetypes[source_idx].has_type(*type_id)),
+            _ => assert!(changed
+                .iter()
+                .all(|&type_id| !self.archetypes[source_idx].has_type(type_id))),
+        }
+        if changed == TypeBundle::default() {
+            return Ok(());
+        }
+
+        // edges only cache migrations of a single type
+        let mut changed_iter = changed.iter();
+        let edge: Option<TypeId> = match (changed_iter.next(), changed_iter.next()) {
+            (Some(&type_id), None) => Some(type_id),
+            _ => None,
         };
         let moved: EntityId;
         let new_row: usize;
 
-        let target_idx: usize = if let Some(&target_idx) =
-            self.archetypes[source_idx].edges.get(&new_type)
+        let target_idx: usize = if let Some(target_idx) =
+            edge.and_then(|type_id| self.archetypes[source_idx].get_edge(type_id))
         {
-            let (source, target) = self.mutate_archetypes(source_idx, target_idx);
-            (moved, new_row) = source.migrate(target, location.row, op);
+            let (source, target) = self
+                .mutate_archetypes(source_idx, target_idx)
+                .expect("migration source and target archetypes must differ");
+            source.check_migration(target, &op)?;
+            (moved, new_row) = source.migrate(target, location.row, op)?;
 
             target_idx
         } else {
             let old_bundle: TypeBundle = self.archetypes[source_idx].types();
-            let type_bundle: TypeBundle = if op.is_add() {
-                old_bundle.add_type(new_type)
-            } else {
-                old_bundle.remove_type(new_type)
+            let type_bundle: TypeBundle = match &op {
+                Migration::Remove(type_id) => old_bundle.remove_type(*type_id),
+                _ => old_bundle.add_types(&changed),
             };
 
             let target_idx: usize = if let Some(target_idx) = self.get_archetype_id(&type_bundle) {
-                let (source, target) = self.mutate_archetypes(source_idx, target_idx);
-                (moved, new_row) = source.migrate(target, location.row, op);
+                let (source, target) = self
+                    .mutate_archetypes(source_idx, target_idx)
+                    .expect("migration source and target archetypes must differ");
+                source.check_migration(target, &op)?;
+                (moved, new_row) = source.migrate(target, location.row, op)?;
 
                 target_idx
             } else {
                 let migration: (EntityId, ComponentBundle) =
-                    self.archetypes[source_idx].migrate_to_bundle(location.row, op);
+                    self.archetypes[source_idx].migrate_to_bundle(location.row, op)?;
 
                 moved = migration.0;
                 new_row = 0;
                 self.push_archetype(migration.1, entity)
             };
 
-            self.archetypes[source_idx]
-                .edges
-                .insert(new_type, target_idx);
-            self.archetypes[target_idx]
-                .edges
-                .insert(new_type, source_idx);
+            if let Some(type_id) = edge {
+                self.archetypes[source_idx].set_edge(type_id, target_idx);
+                self.archetypes[target_idx].set_edge(type_id, source_idx);
+            }
 
             target_idx
         };
 
         self.entities
-            .set_location(entity, Location::new(target_idx, new_row));
-        self.entities.set_location(moved, location);
+            .set_location(entity, Location::new(target_idx, new_row))?;
+        if moved != entity {
+            self.entities.set_location(moved, location)?;
+        }
 
         Ok(())
     }
 
-    pub fn kill(&mut self, entity: EntityId) -> Result<(), EcsError> {
-        let location = self.entities.free(entity)?;
-        self.archetypes[location.archetype].remove(location.row);
+    /// Applies a migration to every given entity, resolving each target archetype only once
+    ///
+    /// `Migration`s own their added component, so `op` builds a fresh one for each entity
+    pub fn migrate_many<O>(&mut self, entities: &[EntityId], mut op: O) -> Result<(), EcsError>
+    where
+        O: FnMut() -> Migration,
+    {
+        let mut targets: HashMap<(usize, TypeBundle), usize> = HashMap::new();
+        for &entity in entities.iter() {
+            let location: Location = self
+                .entities
+                .entity_status(entity)?
+                .ok_or(EntityError::NotFound)?;
+            let Some(migration) = self.migrate_sparse(entity, op())? else {
+                continue;
+            };
+            let key: (usize, TypeBundle) = (location.archetype, migration.types());
+
+            let Some(&target_idx) = targets.get(&key) else {
+                self.migrate(entity, migration)?;
+                let target: Location = self
+                    .entities
+                    .entity_status(entity)?
+                    .ok_or(EntityError::NotFound)?;
+                targets.insert(key, target.archetype);
+                continue;
+            };
+
+            let (source, target) = self
+                .mutate_archetypes(location.archetype, target_idx)
+                .expect("migration source and target archetypes must differ");
+            source.check_migration(target, &migration)?;
+            let (moved, new_row) = source.migrate(target, location.row, migration)?;
+            self.entities
+                .set_location(entity, Location::new(target_idx, new_row))?;
+            if moved != entity {
+                self.entities.set_location(moved, location)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the sparse part of a migration to an entity, returning whatever is left for its
+    /// archetype
+    ///
+    /// Returns `None` once nothing is left, so callers never migrate an entity onto itself.
+    fn migrate_sparse(
+        &mut self,
+        entity: EntityId,
+        op: Migration,
+    ) -> Result<Option<Migration>, EcsError> {
+        let op: Migration = match op {
+            Migration::Add1(comp) if comp.storage_kind() == StorageKind::Sparse => {
+                self.insert_sparse(entity, comp)?;
+                return Ok(None);
+            }
+            Migration::Add(mut bundle) => {
+                for comp in bundle.take_sparse() {
+                    self.insert_sparse(entity, comp)?;
+                }
+                Migration::Add(bundle)
+            }
+            Migration::Remove(type_id) if self.sparse.contains_key(&type_id) => {
+                self.remove_sparse(entity, type_id)
+                    .ok_or(StoreError::TypeNotFound)?;
+                return Ok(None);
+            }
+            op => op,
+        };
+
+        Ok((op.types() != TypeBundle::default()).then_some(op))
+    }
+
+    /// Stores a sparse component of `entity` in the `SparseSet` of its type
+    ///
+    /// Fails if the entity already has a component of that type.
+    fn insert_sparse(&mut self, entity: EntityId, comp: ComponentBox) -> Result<(), StoreError> {
+        let store: &ComponentStore = self.sparse.entry(comp.inner_type_id()).or_insert_with(|| {
+            comp.register_name();
+            comp.new_sparse_store()
+        });
+        if store.inner().entity_row(entity).is_some() {
+            return Err(StoreError::DuplicateType);
+        }
+
+        store.inner_mut().insert_for(entity, comp)?;
+        store.push_changed(1);
 
         Ok(())
     }
 
-    pub fn run_system<M, F>(&self, system: &mut F)
+    /// Removes the sparse component of `entity` matching `type_id`, if it has one
+    fn remove_sparse(&mut self, entity: EntityId, type_id: TypeId) -> Option<ComponentBox> {
+        let store: &ComponentStore = self.sparse.get(&type_id)?;
+        let row: usize = store.inner().entity_row(entity)?;
+        store.swap_remove_changed(row);
+
+        store.inner_mut().swap_remove(row)
+    }
+
+    /// Migrates an entity to gain the component `comp`
+    pub fn migrate_add<T: Component>(&mut self, entity: EntityId, comp: T) -> Result<(), EcsError> {
+        self.migrate(entity, Migration::Add1(comp.into()))
+    }
+
+    /// Migrates many entities to each gain a clone of the component `comp`
+    pub fn migrate_add_many<T: Component + Clone>(
+        &mut self,
+        entities: &[EntityId],
+        comp: T,
+    ) -> Result<(), EcsError> {
+        self.migrate_many(entities, || Migration::Add1(comp.clone().into()))
+    }
+
+    /// Migrates an entity to lose its `T` component
+    pub fn migrate_remove<T: Component>(&mut self, entity: EntityId) -> Result<(), EcsError> {
+        self.migrate(entity, Migration::Remove(TypeId::of::<T>()))
+    }
+
+    /// Adds the component `comp` to an entity, migrating it to the matching archetype
+    ///
+    /// Unlike `migrate_add`, fails rather than panicking if the entity already has a `T`.
+    pub fn insert_component<T: Component>(
+        &mut self,
+        entity: EntityId,
+        comp: T,
+    ) -> Result<(), EcsError> {
+        if self.has_component::<T>(entity)? {
+            return Err(StoreError::DuplicateType.into());
+        }
+
+        self.migrate(entity, Migration::Add1(comp.into()))
+    }
+
+    /// Removes the `T` component of an entity, migrating it to the matching archetype
+    ///
+    /// Unlike `migrate_remove`, fails rather than panicking if the entity has no `T`.
+    pub fn remove_component<T: Component>(&mut self, entity: EntityId) -> Result<(), EcsError> {
+        if !self.has_component::<T>(entity)? {
+            return Err(StoreError::TypeNotFound.into());
+        }
+
+        self.migrate(entity, Migration::Remove(TypeId::of::<T>()))
+    }
+
+    /// Migrates many entities to each lose their `T` component
+    pub fn migrate_remove_many<T: Component>(
+        &mut self,
+        entities: &[EntityId],
+    ) -> Result<(), EcsError> {
+        self.migrate_many(entities, || Migration::Remove(TypeId::of::<T>()))
+    }
+
+    /// Moves the components of `M` out of every entity holding them, returning one owned row per
+    /// entity
+    ///
+    /// Entities stay alive with their remaining components, migrating to the matching archetype.
+    pub fn take_components<M: OwnedQueryModel>(&mut self) -> Result<Vec<M>, EcsError> {
+        let types: TypeBundle = M::get_types();
+        let sources: Vec<usize> = (1..self.archetypes.len())
+            .filter(|&idx| self.archetypes[idx].types_ref().is_superset_of(&types))
+            .collect();
+
+        let mut rows: Vec<M> = Vec::new();
+        for source_idx in sources {
+            // popping the last row means no other entity is moved
+            while let Some(row) = self.archetypes[source_idx].len().checked_sub(1) {
+                let (entity, bundle) = self.archetypes[source_idx].remove(row)?;
+                let (taken, kept): (Vec<ComponentBox>, Vec<ComponentBox>) = bundle
+                    .component_iter()
+                    .partition(|comp| types.iter().any(|&type_id| type_id == comp.inner_type_id()));
+                rows.push(M::from_components(taken)?);
+
+                let mut remaining: ComponentBundle = ComponentBundle::default();
+                kept.into_iter().for_each(|comp| remaining.push_box(comp));
+                self.place_entity(entity, remaining)?;
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Replaces the `T` component of an entity, returning the previous value if there was one
+    ///
+    /// The replaced component is flagged as changed. Entities without a `T` component are migrated
+    /// to gain `new_val`.
+    pub fn replace_component<T: Component>(
+        &mut self,
+        entity: EntityId,
+        new_val: T,
+    ) -> Result<Option<T>, EcsError> {
+        self.entities
+            .entity_status(entity)?
+            .ok_or(EntityError::NotFound)?;
+
+        match self.component_slot::<T>(entity) {
+            Ok((store, row)) => {
+                let mut comp: ComponentMut<T> =
+                    ComponentMut::new(store, row).ok_or(StoreError::CannotCastToType)?;
+
+                Ok(Some(std::mem::replace(&mut *comp, new_val)))
+            }
+            Err(EcsError::Store(StoreError::TypeNotFound)) => {
+                self.migrate(entity, Migration::Add1(new_val.into()))?;
+
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Applies a migration to every entity passing `predicate`, returning how many were migrated
+    ///
+    /// `Migration`s own their added component, so `op` builds a fresh one for each entity
+    pub fn partial_migrate<P, O>(
+        &mut self,
+        entities: &[EntityId],
+        predicate: P,
+        mut op: O,
+    ) -> Result<usize, EcsError>
     where
-        M: QueryModel,
-        for<'m> F: FnMut(M::Row<'m>),
+        P: Fn(EntityId) -> bool,
+        O: FnMut() -> Migration,
     {
-        let bundle: TypeBundle = M::get_types();
-        let archetypes: Vec<&Archetype> = self.get_archetypes_inclusive(&bundle);
-        for &at in archetypes.iter() {
-            let reads: Vec<ReadGuard> = M::get_reads(at);
-            let writes: Vec<WriteGuard> = M::get_writes(at);
-            M::process(reads, writes, system);
+        let mut count: usize = 0;
+        for &entity in entities.iter().filter(|&&entity| predicate(entity)) {
+            self.migrate(entity, op())?;
+            count += 1;
         }
+
+        Ok(count)
     }
 
-    pub fn get_archetypes_inclusive(&self, types: &TypeBundle) -> Vec<&Archetype> {
-        self.inclusive_index
-            .get(types)
-            .cloned()
-            .unwrap_or_default()
-            .iter()
-            .map(|idx| &self.archetypes[*idx])
-            .collect()
+    /// Merges the `T` component of `source` into the `T` component of `target`
+    ///
+    /// `merge_fn` receives the source value and the current target value, returning the new
+    /// target value, which is flagged as changed. Does nothing if `source` does not have a `T`
+    /// component.
+    pub fn merge_component_stores<T, F>(
+        &mut self,
+        source_entity: EntityId,
+        target_entity: EntityId,
+        mut merge_fn: F,
+    ) -> Result<(), EcsError>
+    where
+        T: Component,
+        F: FnMut(&T, T) -> T,
+    {
+        if source_entity == target_entity {
+            return Err(EcsError::SameEntity);
+        }
+
+        let type_id: TypeId = TypeId::of::<T>();
+        let source: Location = self
+            .entities
+            .entity_status(source_entity)?
+            .ok_or(EntityError::NotFound)?;
+        let target: Location = self
+            .entities
+            .entity_status(target_entity)?
+            .ok_or(EntityError::NotFound)?;
+
+        if T::storage_kind() == StorageKind::Sparse {
+            if !self.has_component::<T>(source_entity)? {
+                return Ok(());
+            }
+            let current: T = self
+                .remove_sparse(target_entity, type_id)
+                .ok_or(StoreError::StorageNotFound)?
+                .cast_inner::<T>()?;
+            let merged: T = {
+                let source: ComponentRef<T> = self
+                    .get_component::<T>(source_entity)
+                    .ok_or(StoreError::TypeNotFound)?;
+                merge_fn(&source, current)
+            };
+
+            return Ok(self.insert_sparse(target_entity, merged.into())?);
+        }
+        if !self.archetypes[source.archetype].has_type(type_id) {
+            return Ok(());
+        }
+
+        let target_store: &ComponentStore = self.archetypes[target.archetype]
+            .get_storage(type_id)
+            .ok_or(StoreError::StorageNotFound)?;
+        let mut target_guard: WriteGuard = target_store.inner_mut();
+        let target_vec: &mut Vec<T> = target_guard
+            .to_any_mut()
+            .downcast_mut::<Vec<T>>()
+            .ok_or(StoreError::CannotCastToType)?;
+
+        // move the target value out, leaving the last row in its place until the merge is pushed
+        let last: usize = target_vec.len() - 1;
+        let current: T = target_vec.swap_remove(target.row);
+        let merged: T = if source.archetype == target.archetype {
+            let source_row: usize = if source.row == last {
+                target.row
+            } else {
+                source.row
+            };
+            merge_fn(&target_vec[source_row], current)
+        } else {
+            let source_guard: ReadGuard = self.archetypes[source.archetype]
+                .get_storage(type_id)
+                .ok_or(StoreError::StorageNotFound)?
+                .inner();
+            let source_vec: &Vec<T> = source_guard
+                .to_any()
+                .downcast_ref::<Vec<T>>()
+                .ok_or(StoreError::CannotCastToType)?;
+            merge_fn(&source_vec[source.row], current)
+        };
+        target_vec.push(merged);
+        target_vec.swap(target.row, last);
+        target_store.mark_changed(&[target.row]);
+
+        Ok(())
     }
 
-    fn get_archetype_id(&self, types: &TypeBundle) -> Option<usize> {
-        self.index.get(types).copied()
+    /// Overwrites the `T` component of every given entity with the value produced by `f`
+    ///
+    /// Every entity is validated before any value is written, and each archetype's storage is
+    /// locked only once. Every written component is flagged as changed.
+    pub fn apply_initial_values<T, F>(
+        &mut self,
+        entities: &[EntityId],
+        f: F,
+    ) -> Result<(), EcsError>
+    where
+        T: Component,
+        F: Fn(EntityId) -> T,
+    {
+        let type_id: TypeId = TypeId::of::<T>();
+        if T::storage_kind() == StorageKind::Sparse {
+            for &entity in entities.iter() {
+                if !self.has_component::<T>(entity)? {
+                    return Err(StoreError::StorageNotFound.into());
+                }
+            }
+            for &entity in entities.iter() {
+                *self
+                    .get_component_mut::<T>(entity)
+                    .ok_or(StoreError::CannotCastToType)? = f(entity);
+            }
+
+            return Ok(());
+        }
+
+        let mut grouped: HashMap<usize, Vec<(EntityId, usize)>> = HashMap::new();
+        for &entity in entities.iter() {
+            let location: Location = self
+                .entities
+                .entity_status(entity)?
+                .ok_or(EntityError::NotFound)?;
+            if !self.archetypes[location.archetype].has_type(type_id) {
+                return Err(StoreError::StorageNotFound.into());
+            }
+            grouped
+                .entry(location.archetype)
+                .or_default()
+                .push((entity, location.row));
+        }
+
+        for (archetype, rows) in grouped.into_iter() {
+            let store: &ComponentStore = self.archetypes[archetype]
+                .get_storage(type_id)
+                .ok_or(StoreError::StorageNotFound)?;
+            let mut guard: WriteGuard = store.inner_mut();
+            let values: &mut Vec<T> = guard
+                .to_any_mut()
+                .downcast_mut::<Vec<T>>()
+                .ok_or(StoreError::CannotCastToType)?;
+            for &(entity, row) in rows.iter() {
+                values[row] = f(entity);
+            }
+            let rows: Vec<usize> = rows.into_iter().map(|(_, row)| row).collect();
+            store.mark_changed(&rows);
+        }
+
+        Ok(())
     }
 
-    fn push_archetype(&mut self, bundle: ComponentBundle, entity: EntityId) -> usize {
-        let types: TypeBundle = bundle.types();
-        let archetype_id: usize = self.archetypes.len();
-        self.index.insert(types.clone(), archetype_id);
-        self.archetypes.push(Archetype::new(bundle, entity));
-        self.update_inclusive_index(types, archetype_id);
+    /// Removes every entity and archetype, keeping registered systems and components
+    ///
+    /// Entity ids are reset, so previously-issued `EntityId`s may be reissued by later spawns.
+    pub fn despawn_all(&mut self) {
+        self.archetypes.clear();
+        self.archetypes.push(Archetype::default());
+        self.entities = EntityStore::default();
+        self.index.clear();
+        self.index.insert(TypeBundle::default(), 0);
+        self.inclusive_index.clear();
+        self.archetypes_by_type.clear();
+        self.queries_by_type.clear();
+        self.sparse.clear();
+    }
 
-        archetype_id
+    /// Kills the given entity, returning every component it owned
+    pub fn kill(&mut self, entity: EntityId) -> Result<ComponentBundle, EcsError> {
+        let location = self.entities.free(entity)?;
+        let (moved, mut bundle) = self.archetypes[location.archetype].remove(location.row)?;
+        if moved != entity {
+            self.entities.set_location(moved, location)?;
+        }
+        let sparse: Vec<TypeId> = self.sparse.keys().copied().collect();
+        for type_id in sparse {
+            if let Some(comp) = self.remove_sparse(entity, type_id) {
+                bundle.push_box(comp);
+            }
+        }
+
+        Ok(bundle)
     }
 
-    fn update_inclusive_index(&mut self, types: TypeBundle, archetype_id: usize) {
-        self.inclusive_index.iter_mut().for_each(|(t, v)| {
-            if types.contains(t) {
-                v.push(archetype_id)
+    /// Kills every entity matched by the query `M`, returning how many were killed
+    ///
+    /// Each matching archetype is cleared at once rather than row by row, unless a sparse `Without`
+    /// type spares some of its entities. `Changed` filters are ignored, and a query without component types matches nothing, as in `run_system`.
+    pub fn despawn_type<M: QueryModel>(&mut self) -> usize {
+        if M::get_types() == TypeBundle::default() {
+            return 0;
+        }
+
+        let sparse: Vec<TypeId> = self.sparse.keys().copied().collect();
+        let mut despawned: usize = 0;
+        for idx in self.get_query_archetype_ids::<M>() {
+            // rows excluded by a sparse `Without` survive, so only the matched rows are killed
+            if let Some(rows) = self.filter_sparse::<M>(&self.archetypes[idx], None) {
+                let doomed: Vec<EntityId> = {
+                    let entities = self.archetypes[idx].entities();
+                    rows.iter().map(|&row| entities[row]).collect()
+                };
+                for entity in doomed {
+                    self.kill(entity).expect("archetype entities must be alive");
+                    despawned += 1;
+                }
+                continue;
             }
-        });
-        let ids: Vec<usize> = self
-            .index
-            .iter()
-            .filter(|(t, _)| t.contains(&types))
-            .map(|(_, &a)| a)
+
+            for entity in self.archetypes[idx].clear() {
+                self.entities
+                    .free(entity)
+                    .expect("archetype entities must be alive");
+                for &type_id in sparse.iter() {
+                    self.remove_sparse(entity, type_id);
+                }
+                despawned += 1;
+            }
+        }
+
+        despawned
+    }
+
+    /// Kills every given entity, returning how many were killed
+    pub fn kill_batch(&mut self, entities: &[EntityId]) -> Result<usize, EcsError> {
+        for &entity in entities.iter() {
+            self.kill(entity)?;
+        }
+
+        Ok(entities.len())
+    }
+
+    /// Kills every live child listed in the `Children` of `parent`, returning how many were killed
+    ///
+    /// The `Children` component of `parent` is left in place, but emptied.
+    pub fn kill_children_of(&mut self, parent: EntityId) -> Result<usize, EcsError> {
+        let location: Location = self
+            .entities
+            .entity_status(parent)?
+            .ok_or(EntityError::NotFound)?;
+        let Some(store) = self.archetypes[location.archetype].get_storage(TypeId::of::<Children>())
+        else {
+            return Ok(0);
+        };
+
+        let children: Vec<EntityId> = std::mem::take(
+            &mut store
+                .inner_mut()
+                .to_any_mut()
+                .downcast_mut::<Vec<Children>>()
+                .ok_or(StoreError::CannotCastToType)?[location.row]
+                .0,
+        );
+        let alive: Vec<EntityId> = children
+            .into_iter()
+            .filter(|&child| matches!(self.entities.entity_status(child), Ok(Some(_))))
             .collect();
-        self.inclusive_index.insert(types, ids);
+
+        self.kill_batch(&alive)
     }
 
-    fn mutate_archetypes(
-        &mut self,
-        first: usize,
-        second: usize,
-    ) -> (&mut Archetype, &mut Archetype) {
-        assert!(first < second);
-        let (a, b) = self.archetypes.split_at_mut(second);
-        (&mut a[first], &mut b[0])
+    /// Runs a system over every matching row, returning the number of rows processed
+    ///
+    /// Structural changes made in response should be queued as `WorldCommand`s and applied afterwards
+    /// with `flush_commands`.
+    pub fn run_system<M, F>(&mut self, system: &mut F) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>),
+    {
+        let marked: TypeBundle = Self::get_marked_types::<M>();
+
+        let mut processed: usize = 0;
+        for at in self.get_query_archetypes::<M>() {
+            if at.entity_count() == 0 {
+                continue;
+            }
+
+            let query_rows: QueryRows = self.get_query_rows::<M>(at);
+            processed += Self::process_query_rows::<M, F>(
+                at,
+                &marked,
+                &query_rows.taken,
+                query_rows.rows.as_deref(),
+                system,
+            );
+        }
+
+        processed
+    }
+
+    /// Runs a system over every matching row in parallel chunks of 64 rows, returning the number of
+    /// rows processed
+    #[cfg(feature = "parallel")]
+    pub fn run_system_parallel<M, F>(&self, system: F) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: Fn(M::Row<'m>) + Send + Sync,
+    {
+        self.run_system_parallel_chunked::<M, F>(PARALLEL_CHUNK_SIZE, system)
+    }
+
+    /// Runs a system over every matching row, splitting each archetype into chunks of `chunk_size`
+    /// rows that run in parallel, returning the number of rows processed
+    ///
+    /// Guards are acquired once per archetype. Queries with a `Changed` filter process their changed
+    /// rows on the calling thread.
+    #[cfg(feature = "parallel")]
+    pub fn run_system_parallel_chunked<M, F>(&self, chunk_size: usize, system: F) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: Fn(M::Row<'m>) + Send + Sync,
+    {
+        let marked: TypeBundle = Self::get_marked_types::<M>();
+
+        let mut processed: usize = 0;
+        for at in self.get_query_archetypes::<M>() {
+            if at.entity_count() == 0 {
+                continue;
+            }
+
+            let query_rows: QueryRows = self.get_query_rows::<M>(at);
+            match query_rows.rows {
+                Some(rows) => {
+                    processed += Self::process_query_rows::<M, _>(
+                        at,
+                        &marked,
+                        &query_rows.taken,
+                        Some(&rows),
+                        &mut |row: M::Row<'_>| system(row),
+                    );
+                }
+                None => {
+                    let reads: Vec<ReadGuard> = M::get_reads(at);
+                    let writes: Vec<WriteGuard> = M::get_writes(at);
+                    processed += M::process_parallel(reads, writes, chunk_size, &system);
+                    at.mark_all_changed(&marked);
+                }
+            }
+        }
+
+        processed
+    }
+
+    /// Runs a system over every matching row alongside the world's `R` resource, returning the
+    /// number of rows processed
+    ///
+    /// The resource is taken out of the world while the system runs. Fails if there is no `R`
+    /// resource.
+    pub fn run_system_with_resource<M, R, F>(&mut self, system: &mut F) -> Result<usize, EcsError>
+    where
+        M: QueryModel,
+        R: Send + Sync + 'static,
+        for<'m> F: FnMut(M::Row<'m>, &mut R),
+    {
+        let mut resource: R = self
+            .remove_resource::<R>()
+            .ok_or(EcsError::ResourceNotFound)?;
+        let processed: usize =
+            self.run_system::<M, _>(&mut |row: M::Row<'_>| system(row, &mut resource));
+        self.insert_resource(resource);
+
+        Ok(processed)
+    }
+
+    /// Runs a system over the only entity matching the query, returning the system's result
+    ///
+    /// Fails if no entity or more than one entity matches. `Changed` filters are ignored.
+    pub fn query_single<M, F, R>(&mut self, system: F) -> Result<R, EcsError>
+    where
+        M: QueryModel,
+        for<'m> F: FnOnce(M::Row<'m>) -> R,
+    {
+        let archetypes: Vec<&Archetype> = self.get_query_archetypes::<M>();
+        let matched: usize = archetypes
+            .iter()
+            .map(|at| {
+                self.filter_sparse::<M>(at, None)
+                    .map_or(at.len(), |rows| rows.len())
+            })
+            .sum();
+        match matched {
+            0 => Err(EntityError::NotFound.into()),
+            1 => self.query_single_unchecked::<M, F, R>(system),
+            _ => Err(EcsError::MultipleMatches),
+        }
+    }
+
+    /// Runs a system over the first entity matching the query, returning the system's result
+    ///
+    /// Any further matching entities are skipped. Fails if no entity matches.
+    pub fn query_single_unchecked<M, F, R>(&mut self, system: F) -> Result<R, EcsError>
+    where
+        M: QueryModel,
+        for<'m> F: FnOnce(M::Row<'m>) -> R,
+    {
+        let (at, row): (&Archetype, usize) = self
+            .get_query_archetypes::<M>()
+            .into_iter()
+            .filter(|at| at.entity_count() > 0)
+            .find_map(|at| match self.filter_sparse::<M>(at, None) {
+                Some(rows) => rows.first().map(|&row| (at, row)),
+                None => Some((at, 0)),
+            })
+            .ok_or(EntityError::NotFound)?;
+
+        let mut system: Option<F> = Some(system);
+        let mut result: Option<R> = None;
+        let reads: Vec<ReadGuard> = M::get_reads(at);
+        let writes: Vec<WriteGuard> = M::get_writes(at);
+        M::process_rows(reads, writes, &[row], &mut |row: M::Row<'_>| {
+            result = system.take().map(|system| system(row));
+        });
+        at.mark_changed(&Self::get_marked_types::<M>(), &[row]);
+
+        result.ok_or(EntityError::NotFound.into())
+    }
+
+    /// Kills every matching entity for which `predicate` returns `false`, returning how many were
+    /// killed
+    ///
+    /// Entities are only killed once every matching row has been visited. `Changed` filters are
+    /// ignored.
+    pub fn retain<M, F>(&mut self, mut predicate: F) -> Result<usize, EcsError>
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>) -> bool,
+    {
+        let mut doomed: Vec<EntityId> = Vec::new();
+        for at in self.get_query_archetypes::<M>() {
+            if at.entity_count() == 0 {
+                continue;
+            }
+
+            // rows are processed in order, so each result lines up with the entity at its row
+            let rows: Vec<usize> = self
+                .filter_sparse::<M>(at, None)
+                .unwrap_or_else(|| (0..at.len()).collect());
+            let mut keep: Vec<bool> = Vec::with_capacity(rows.len());
+            let reads: Vec<ReadGuard> = M::get_reads(at);
+            let writes: Vec<WriteGuard> = M::get_writes(at);
+            M::process_rows(reads, writes, &rows, &mut |row: M::Row<'_>| {
+                keep.push(predicate(row))
+            });
+            let entities = at.entities();
+            doomed.extend(
+                rows.iter()
+                    .zip(keep)
+                    .filter(|(_, keep)| !keep)
+                    .map(|(&row, _)| entities[row]),
+            );
+        }
+
+        self.kill_batch(&doomed)
+    }
+
+    /// Applies queued commands in order, stopping at the first that fails
+    pub fn flush_commands(&mut self, commands: Vec<WorldCommand>) -> Result<(), EcsError> {
+        for command in commands.into_iter() {
+            match command {
+                WorldCommand::Spawn(bundle) => {
+                    self.spawn(bundle)?;
+                }
+                WorldCommand::Kill(entity) => {
+                    self.kill(entity)?;
+                }
+                WorldCommand::Migrate(entity, op) => self.migrate(entity, op)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a system over every matching archetype that does not contain the component `C`,
+    /// returning the number of rows processed
+    pub fn run_system_unless<M, F, C>(&mut self, system: &mut F) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>),
+        C: Component,
+    {
+        let marked: TypeBundle = Self::get_marked_types::<M>();
+
+        let mut processed: usize = 0;
+        for at in self
+            .get_query_archetypes::<M>()
+            .into_iter()
+            .filter(|at| !at.has_type(TypeId::of::<C>()))
+        {
+            if at.entity_count() == 0 {
+                continue;
+            }
+
+            let mut query_rows: QueryRows = self.get_query_rows::<M>(at);
+            query_rows.rows = self.without_sparse(at, TypeId::of::<C>(), query_rows.rows);
+            processed += Self::process_query_rows::<M, F>(
+                at,
+                &marked,
+                &query_rows.taken,
+                query_rows.rows.as_deref(),
+                system,
+            );
+        }
+
+        processed
+    }
+
+    /// Runs a system only over matching entities whose index falls within `id_range`, returning
+    /// the number of rows processed
+    pub fn run_system_for_range<M, F>(&mut self, id_range: Range<u32>, system: &mut F) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>),
+    {
+        let marked: TypeBundle = Self::get_marked_types::<M>();
+
+        let mut processed: usize = 0;
+        for at in self.get_query_archetypes::<M>() {
+            let query_rows: QueryRows = self.get_query_rows::<M>(at);
+            let rows: Vec<usize> = {
+                let entities = at.entities();
+                query_rows
+                    .selected(at)
+                    .into_iter()
+                    .filter(|&row| id_range.contains(&entities[row].index()))
+                    .collect()
+            };
+            processed += Self::process_query_rows::<M, F>(
+                at,
+                &marked,
+                &query_rows.taken,
+                Some(&rows),
+                system,
+            );
+        }
+
+        processed
+    }
+
+    /// Runs a system over the `count` matching entities with the highest indices, returning the
+    /// number of rows processed
+    pub fn run_system_on_newest<M, F>(&mut self, count: usize, system: &mut F) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>),
+    {
+        let marked: TypeBundle = Self::get_marked_types::<M>();
+        let archetypes: Vec<&Archetype> = self.get_query_archetypes::<M>();
+        let query_rows: Vec<QueryRows> = archetypes
+            .iter()
+            .map(|at| self.get_query_rows::<M>(at))
+            .collect();
+        let mut newest: Vec<(u32, usize, usize)> = archetypes
+            .iter()
+            .zip(query_rows.iter())
+            .enumerate()
+            .flat_map(|(at_idx, (at, query_rows))| {
+                let entities = at.entities();
+                query_rows
+                    .selected(at)
+                    .into_iter()
+                    .map(|row| (entities[row].index(), at_idx, row))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        newest.sort_unstable_by(|a, b| b.cmp(a));
+        newest.truncate(count);
+
+        let mut processed: usize = 0;
+        for (at_idx, (&at, query_rows)) in archetypes.iter().zip(query_rows.iter()).enumerate() {
+            let rows: Vec<usize> = newest
+                .iter()
+                .filter(|(_, idx, _)| *idx == at_idx)
+                .map(|&(_, _, row)| row)
+                .collect();
+            processed += Self::process_query_rows::<M, F>(
+                at,
+                &marked,
+                &query_rows.taken,
+                Some(&rows),
+                system,
+            );
+        }
+
+        processed
+    }
+
+    /// Runs a system over a single page of matching rows, returning how many rows were processed
+    ///
+    /// Pages are stable between calls as long as no entities are spawned, killed, or migrated.
+    pub fn run_system_paginated<M, F>(
+        &mut self,
+        page: usize,
+        page_size: usize,
+        system: &mut F,
+    ) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>),
+    {
+        let marked: TypeBundle = Self::get_marked_types::<M>();
+        let mut skip: usize = page * page_size;
+        let mut remaining: usize = page_size;
+        for at in self.get_query_archetypes::<M>() {
+            if remaining == 0 {
+                break;
+            }
+
+            let query_rows: QueryRows = self.get_query_rows::<M>(at);
+            let passed: Vec<usize> = query_rows.selected(at);
+            let rows: &[usize] = if skip >= passed.len() {
+                skip -= passed.len();
+                &[]
+            } else {
+                let end: usize = passed.len().min(skip + remaining);
+                let rows: &[usize] = &passed[skip..end];
+                remaining -= rows.len();
+                skip = 0;
+                rows
+            };
+            Self::process_query_rows::<M, F>(at, &marked, &query_rows.taken, Some(rows), system);
+        }
+
+        page_size - remaining
+    }
+
+    /// Runs a system over every matching row, collecting each returned value
+    pub fn run_system_collecting<M, R, F>(&mut self, system: &mut F) -> Vec<R>
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>) -> R,
+    {
+        let mut collected: Vec<R> = Vec::new();
+        self.run_system::<M, _>(&mut |row: M::Row<'_>| collected.push(system(row)));
+
+        collected
+    }
+
+    /// Runs a system, measuring the time spent across all matching archetypes along with the
+    /// number of rows processed
+    pub fn profile_run_system<M, F>(&mut self, system: &mut F) -> (Duration, usize)
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>),
+    {
+        let start: Instant = Instant::now();
+        let processed: usize = self.run_system::<M, F>(system);
+
+        (start.elapsed(), processed)
+    }
+
+    /// Runs a collecting system, measuring the time spent across all matching archetypes
+    pub fn profile_run_system_collecting<M, R, F>(&mut self, system: &mut F) -> (Duration, Vec<R>)
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>) -> R,
+    {
+        let start: Instant = Instant::now();
+        let collected: Vec<R> = self.run_system_collecting::<M, R, F>(system);
+
+        (start.elapsed(), collected)
+    }
+
+    /// Registers a system to be run by `run_systems`, ordered by registration unless constrained
+    pub fn add_system<M, F>(&mut self, mut system: F) -> SystemId
+    where
+        M: QueryModel + 'static,
+        for<'m> F: FnMut(M::Row<'m>) + 'static,
+    {
+        let system_id: SystemId = SystemId(self.systems.len());
+        self.systems.push(Box::new(move |world: &mut World| {
+            world.run_system::<M, F>(&mut system);
+        }));
+
+        system_id
+    }
+
+    /// Registers a system that is guaranteed to run before the `before` system
+    pub fn add_system_before<M, F>(
+        &mut self,
+        system: F,
+        before: SystemId,
+    ) -> Result<SystemId, EcsError>
+    where
+        M: QueryModel + 'static,
+        for<'m> F: FnMut(M::Row<'m>) + 'static,
+    {
+        self.check_system(before)?;
+        let system_id: SystemId = self.add_system::<M, F>(system);
+        self.add_system_order(system_id, before)?;
+
+        Ok(system_id)
+    }
+
+    /// Registers a system that is guaranteed to run after the `after` system
+    pub fn add_system_after<M, F>(
+        &mut self,
+        system: F,
+        after: SystemId,
+    ) -> Result<SystemId, EcsError>
+    where
+        M: QueryModel + 'static,
+        for<'m> F: FnMut(M::Row<'m>) + 'static,
+    {
+        self.check_system(after)?;
+        let system_id: SystemId = self.add_system::<M, F>(system);
+        self.add_system_order(after, system_id)?;
+
+        Ok(system_id)
+    }
+
+    /// Runs every registered system once, respecting declared orderings
+    pub fn run_systems(&mut self) {
+        let order: Vec<usize> = self
+            .sort_systems()
+            .expect("system ordering is validated on registration");
+        let mut systems: Vec<BoxedSystem> = std::mem::take(&mut self.systems);
+        for idx in order {
+            systems[idx](self);
+        }
+        self.systems = systems;
+    }
+
+    pub fn get_archetype(&self, idx: usize) -> Option<&Archetype> {
+        self.archetypes.get(idx)
+    }
+
+    /// Gets a read guard over the entire `T` storage of the archetype at `archetype_idx`
+    ///
+    /// Fails if `T` uses sparse storage, as sparse components are never stored in an archetype.
+    pub fn get_component_range<T: Component>(
+        &self,
+        archetype_idx: usize,
+    ) -> Result<ReadGuard<'_>, EcsError> {
+        if T::storage_kind() == StorageKind::Sparse {
+            return Err(StoreError::WrongStorageKind.into());
+        }
+
+        Ok(self
+            .archetypes
+            .get(archetype_idx)
+            .ok_or(EcsError::ArchetypeNotFound)?
+            .get_storage(TypeId::of::<T>())
+            .ok_or(StoreError::StorageNotFound)?
+            .inner())
+    }
+
+    /// Checks whether the given entity is alive and located in an archetype
+    pub fn contains(&self, entity: EntityId) -> bool {
+        matches!(self.entities.entity_status(entity), Ok(Some(_)))
+    }
+
+    /// Checks whether the given entity has a `T` component without locking any storage
+    pub fn has_component<T: Component>(&self, entity: EntityId) -> Result<bool, EntityError> {
+        let location: Location = self
+            .entities
+            .entity_status(entity)
+            .ok()
+            .flatten()
+            .ok_or(EntityError::NotFound)?;
+        if T::storage_kind() == StorageKind::Sparse {
+            return Ok(self.sparse_row::<T>(entity).is_some());
+        }
+
+        Ok(self.archetypes[location.archetype].has_type(TypeId::of::<T>()))
+    }
+
+    /// Gets a read reference to the `T` component of the given entity, if it is alive and has one
+    pub fn get_component<T: Component>(&self, entity: EntityId) -> Option<ComponentRef<'_, T>> {
+        let location: Location = self.entities.entity_status(entity).ok()??;
+        if T::storage_kind() == StorageKind::Sparse {
+            let (store, row) = self.sparse_row::<T>(entity)?;
+            return ComponentRef::new(store.inner(), row);
+        }
+        let store: &ComponentStore =
+            self.archetypes[location.archetype].get_storage(TypeId::of::<T>())?;
+
+        ComponentRef::new(store.inner(), location.row)
+    }
+
+    /// Gets a write reference to the `T` component of the given entity, if it is alive and has one
+    ///
+    /// Writing through the reference flags the component as changed once it is dropped.
+    pub fn get_component_mut<T: Component>(&self, entity: EntityId) -> Option<ComponentMut<'_, T>> {
+        let location: Location = self.entities.entity_status(entity).ok()??;
+        if T::storage_kind() == StorageKind::Sparse {
+            let (store, row) = self.sparse_row::<T>(entity)?;
+            return ComponentMut::new(store, row);
+        }
+        let store: &ComponentStore =
+            self.archetypes[location.archetype].get_storage(TypeId::of::<T>())?;
+
+        ComponentMut::new(store, location.row)
+    }
+
+    /// Exchanges the `T` components of two entities, flagging both as changed
+    ///
+    /// Entities in different archetypes swap values between their stores, so neither is migrated.
+    /// Fails if either entity is dead or has no `T`.
+    pub fn swap_components<T: Component>(
+        &mut self,
+        a: EntityId,
+        b: EntityId,
+    ) -> Result<(), EcsError> {
+        let (store_a, row_a) = self.component_slot::<T>(a)?;
+        let (store_b, row_b) = self.component_slot::<T>(b)?;
+
+        if std::ptr::eq(store_a, store_b) {
+            column_mut::<T>(&mut *store_a.inner_mut())
+                .ok_or(StoreError::CannotCastToType)?
+                .swap(row_a, row_b);
+            store_a.mark_changed(&[row_a, row_b]);
+        } else {
+            let mut comp_a: ComponentMut<T> =
+                ComponentMut::new(store_a, row_a).ok_or(StoreError::CannotCastToType)?;
+            let mut comp_b: ComponentMut<T> =
+                ComponentMut::new(store_b, row_b).ok_or(StoreError::CannotCastToType)?;
+            std::mem::swap(&mut *comp_a, &mut *comp_b);
+        }
+
+        Ok(())
+    }
+
+    /// Gets the store holding the `T` component of `entity` alongside its row
+    fn component_slot<T: Component>(
+        &self,
+        entity: EntityId,
+    ) -> Result<(&ComponentStore, usize), EcsError> {
+        let location: Location = self
+            .entities
+            .entity_status(entity)
+            .ok()
+            .flatten()
+            .ok_or(EntityError::NotFound)?;
+        if T::storage_kind() == StorageKind::Sparse {
+            return self
+                .sparse_row::<T>(entity)
+                .ok_or(StoreError::TypeNotFound.into());
+        }
+        let store: &ComponentStore = self.archetypes[location.archetype]
+            .get_storage(TypeId::of::<T>())
+            .ok_or(StoreError::TypeNotFound)?;
+
+        Ok((store, location.row))
+    }
+
+    /// Gets the `SparseSet` store of `T` alongside the row holding the component of `entity`
+    fn sparse_row<T: Component>(&self, entity: EntityId) -> Option<(&ComponentStore, usize)> {
+        let store: &ComponentStore = self.sparse.get(&TypeId::of::<T>())?;
+        let row: usize = store.inner().entity_row(entity)?;
+
+        Some((store, row))
+    }
+
+    /// Gets the archetype currently storing the given entity, or `None` if it is dead or unlocated
+    pub fn get_archetype_for_entity(&self, entity: EntityId) -> Option<&Archetype> {
+        self.get_archetype_for_entity_ref(entity).ok()
+    }
+
+    /// Gets the archetype currently storing the given entity
+    pub fn get_archetype_for_entity_ref(&self, entity: EntityId) -> Result<&Archetype, EcsError> {
+        let location: Location = self
+            .entities
+            .entity_status(entity)?
+            .ok_or(EntityError::NotFound)?;
+
+        self.archetypes
+            .get(location.archetype)
+            .ok_or(EcsError::ArchetypeNotFound)
+    }
+
+    /// Iterates over every live entity, archetype by archetype
+    ///
+    /// Each archetype's entities are copied out when it is reached, so no lock is held between items.
+    pub fn iter_entities(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.archetypes.iter().flat_map(|at| at.entities().clone())
+    }
+
+    /// Iterates over every live entity by index, paired with the index of its archetype
+    pub fn iter_entity_archetypes(&self) -> impl Iterator<Item = (EntityId, usize)> + '_ {
+        self.entities
+            .iter_alive()
+            .map(|(entity, location)| (entity, location.archetype))
+    }
+
+    /// Gets the archetype for the component layout of the archetype at `idx`, returning its index
+    ///
+    /// Only the layout is read, never the entities. Each `TypeBundle` maps to a single archetype, so
+    /// this returns the archetype already indexed for the layout, which is usually `idx` itself.
+    pub fn duplicate_archetype(&mut self, idx: usize) -> Result<usize, EcsError> {
+        let types: TypeBundle = self
+            .archetypes
+            .get(idx)
+            .ok_or(EcsError::ArchetypeNotFound)?
+            .types();
+
+        Ok(self.get_or_create_archetype_for(types, idx))
+    }
+
+    /// Empties archetypes holding fewer than `min_entities_threshold` entities into compatible ones
+    ///
+    /// An archetype is compatible if it shares the same types, or has exactly one extra registered
+    /// type which is filled with its default value. Entities move into the most populated
+    /// compatible archetype no smaller than their own. Every archetype left empty is then removed
+    /// with `compact_edges`, returning the number of archetypes removed.
+    pub fn compact_archetypes(&mut self, min_entities_threshold: usize) -> usize {
+        let archetypes: &Vec<Archetype> = &self.archetypes;
+        let registry: &HashMap<TypeId, ComponentInfo> = &self.registry;
+
+        for source_idx in 1..archetypes.len() {
+            let source: &Archetype = &archetypes[source_idx];
+            let len: usize = source.len();
+            if len == 0 || len >= min_entities_threshold {
+                continue;
+            }
+
+            let source_types: &TypeBundle = source.types_ref();
+            let compatible = |target: &Archetype| {
+                let extras: Vec<TypeId> = target
+                    .types_ref()
+                    .iter()
+                    .filter(|&&type_id| !source.has_type(type_id))
+                    .copied()
+                    .collect();
+                target.types_ref().is_superset_of(source_types)
+                    && extras.len() <= 1
+                    && extras.iter().all(|type_id| registry.contains_key(type_id))
+            };
+            let Some(target_idx) = (1..archetypes.len())
+                .filter(|&idx| idx != source_idx)
+                .filter(|&idx| archetypes[idx].len() >= len && compatible(&archetypes[idx]))
+                .max_by_key(|&idx| (archetypes[idx].len(), std::cmp::Reverse(idx)))
+            else {
+                continue;
+            };
+
+            let target: &Archetype = &archetypes[target_idx];
+            while !source.is_empty() {
+                let (entity, new_row) = source
+                    .transfer(target, source.len() - 1, |type_id| {
+                        registry.get(&type_id).map(|info| info.default_box())
+                    })
+                    .expect("compatible archetypes should accept a transfer");
+                self.entities
+                    .set_location(entity, Location::new(target_idx, new_row))
+                    .expect("transferred entities should be alive");
+            }
+        }
+
+        let count: usize = self.archetypes.len();
+        self.compact_edges();
+
+        count - self.archetypes.len()
+    }
+
+    /// Removes every archetype without entities along with the edges leading to it, returning how
+    /// many edges were removed
+    ///
+    /// The remaining archetypes are shifted down to fill the gaps, so archetype indices are not
+    /// stable across calls. Intended for level transitions after bulk removal.
+    pub fn compact_edges(&mut self) -> usize {
+        // the index of each archetype once the empty ones are removed, or `None` if it is removed
+        let mut kept: usize = 0;
+        let remap: Vec<Option<usize>> = self
+            .archetypes
+            .iter()
+            .enumerate()
+            .map(|(idx, at)| {
+                (idx == 0 || at.entity_count() > 0).then(|| {
+                    kept += 1;
+                    kept - 1
+                })
+            })
+            .collect();
+
+        let removed: usize = self
+            .archetypes
+            .iter_mut()
+            .zip(remap.iter())
+            .map(|(at, new_idx)| match new_idx {
+                Some(_) => at.remap_edges(|target| remap[target]),
+                None => at.edge_count(),
+            })
+            .sum();
+        let mut old_idx: usize = 0;
+        self.archetypes.retain(|_| {
+            old_idx += 1;
+            remap[old_idx - 1].is_some()
+        });
+
+        let shifted = remap
+            .iter()
+            .enumerate()
+            .filter_map(|(old_idx, new_idx)| new_idx.filter(|&new_idx| new_idx != old_idx));
+        for new_idx in shifted {
+            for (row, &entity) in self.archetypes[new_idx].entities().iter().enumerate() {
+                self.entities
+                    .set_location(entity, Location::new(new_idx, row))
+                    .expect("archetype entities should be alive");
+            }
+        }
+        self.index = self
+            .index
+            .drain()
+            .filter_map(|(types, idx)| remap[idx].map(|idx| (types, idx)))
+            .collect();
+        self.inclusive_index
+            .values_mut()
+            .chain(self.archetypes_by_type.values_mut())
+            .for_each(|ids| *ids = ids.iter().filter_map(|&idx| remap[idx]).collect());
+
+        removed
+    }
+
+    /// Caches the archetypes matched by the query `M`, so later runs never scan for them
+    ///
+    /// Archetypes pushed afterwards are added to the cached entry as they are created.
+    ///
+    /// Panics if `M` reads or writes a sparse component, as in every query runner.
+    pub fn register_query<M: QueryModel>(&mut self) {
+        Self::check_query_storage::<M>();
+        self.cache_inclusive_query(M::get_types());
+    }
+
+    /// Gets the number of archetypes, including empty ones
+    pub fn archetype_count(&self) -> usize {
+        self.archetypes.len()
+    }
+
+    /// Gets the number of live entities
+    pub fn entity_count(&self) -> usize {
+        self.entities.alive_count()
+    }
+
+    /// Names an entity for debug output, replacing any previous name
+    #[cfg(feature = "debug-names")]
+    pub fn name_entity(&mut self, id: EntityId, name: &str) -> Result<(), EntityError> {
+        self.entities.set_name(id, name)
+    }
+
+    /// Gets the debug name of an entity, if one has been set
+    #[cfg(feature = "debug-names")]
+    pub fn entity_name(&self, id: EntityId) -> Result<Option<&str>, EntityError> {
+        self.entities.get_name(id)
+    }
+
+    /// Sums the rows stored across every archetype, which should always equal `entity_count`
+    pub fn get_row_count(&self) -> usize {
+        self.archetypes.iter().map(|at| at.len()).sum()
+    }
+
+    /// Asserts that the world's internal bookkeeping is consistent
+    pub fn validate(&self) {
+        assert_eq!(
+            self.get_row_count(),
+            self.entity_count(),
+            "archetype rows do not match live entities"
+        );
+        for archetype in self.archetypes.iter() {
+            debug_assert_eq!(
+                archetype.entity_count(),
+                archetype.entities().len(),
+                "archetype entity count out of sync"
+            );
+            for &type_id in archetype.types_ref().iter() {
+                debug_assert_eq!(
+                    archetype.entity_count(),
+                    archetype
+                        .get_storage(type_id)
+                        .map_or(0, |store| store.len()),
+                    "archetype storage length out of sync"
+                );
+            }
+        }
+    }
+
+    /// Gets every archetype containing at least the given types
+    ///
+    /// Falls back to scanning all archetypes when `types` has no entry in the inclusive index
+    pub fn get_archetypes_inclusive(&self, types: &TypeBundle) -> Vec<&Archetype> {
+        match self.inclusive_index.get(types) {
+            Some(ids) => ids.iter().map(|idx| &self.archetypes[*idx]).collect(),
+            None => self
+                .find_archetypes_inclusive(types)
+                .into_iter()
+                .map(|idx| &self.archetypes[idx])
+                .collect(),
+        }
+    }
+
+    /// Finds every archetype containing `types` by only scanning those holding its rarest type
+    fn find_archetypes_inclusive(&self, types: &TypeBundle) -> Vec<usize> {
+        let Some(rarest) = types
+            .iter()
+            .map(|type_id| {
+                self.archetypes_by_type
+                    .get(type_id)
+                    .map_or(&[][..], Vec::as_slice)
+            })
+            .min_by_key(|ids| ids.len())
+        else {
+            return (0..self.archetypes.len()).collect();
+        };
+
+        rarest
+            .iter()
+            .copied()
+            .filter(|&idx| {
+                types
+                    .iter()
+                    .all(|&type_id| self.archetypes[idx].has_type(type_id))
+            })
+            .collect()
+    }
+
+    /// Counts every entity with a `T` component
+    pub fn get_component_count<T: Component>(&self) -> usize {
+        self.get_component_count_by_type_id(TypeId::of::<T>())
+    }
+
+    /// Counts every entity with a component matching `type_id`
+    pub fn get_component_count_by_type_id(&self, type_id: TypeId) -> usize {
+        let sparse: usize = self.sparse.get(&type_id).map_or(0, ComponentStore::len);
+
+        self.get_archetypes_inclusive(&TypeBundle::from([type_id].as_slice()))
+            .iter()
+            .map(|at| at.len())
+            .sum::<usize>()
+            + sparse
+    }
+
+    /// Gets every archetype matched by the query `M`, skipping any containing an excluded type
+    fn get_query_archetypes<M: QueryModel>(&self) -> Vec<&Archetype> {
+        self.get_query_archetype_ids::<M>()
+            .into_iter()
+            .map(|idx| &self.archetypes[idx])
+            .collect()
+    }
+
+    /// Gets the index of every archetype matched by the query `M`
+    ///
+    /// Panics if `M` reads or writes a sparse component, which would otherwise match nothing.
+    fn get_query_archetype_ids<M: QueryModel>(&self) -> Vec<usize> {
+        Self::check_query_storage::<M>();
+        let types: TypeBundle = M::get_types();
+        let excluded: TypeBundle = M::get_excluded();
+        self.inclusive_index
+            .get(&types)
+            .cloned()
+            .unwrap_or_else(|| self.find_archetypes_inclusive(&types))
+            .into_iter()
+            .filter(|&idx| {
+                !excluded
+                    .iter()
+                    .any(|&type_id| self.archetypes[idx].has_type(type_id))
+            })
+            .collect()
+    }
+
+    /// Panics if the query `M` reads or writes a sparse component
+    ///
+    /// Sparse components are never stored in an archetype, so such a query could never match.
+    fn check_query_storage<M: QueryModel>() {
+        let sparse: TypeBundle = M::get_sparse_types();
+        assert!(
+            sparse == TypeBundle::default(),
+            "query {} reads or writes sparse components ({sparse}), which queries cannot fetch; \
+             use `Without` to filter on them or `World::get_component` to read them",
+            std::any::type_name::<M>(),
+        );
+    }
+
+    /// Gets the rows of `at` passing the filters of the query `M`
+    ///
+    /// Takes the changed flags of any `Changed` filter, so the result must be passed on to
+    /// `process_query_rows`.
+    fn get_query_rows<M: QueryModel>(&self, at: &Archetype) -> QueryRows {
+        let changed: Option<Vec<usize>> = M::get_changed(at).map(|changed| {
+            changed
+                .iter()
+                .enumerate()
+                .filter(|(_, &changed)| changed)
+                .map(|(row, _)| row)
+                .collect()
+        });
+
+        let rows: Option<Vec<usize>> = self.filter_sparse::<M>(at, changed.clone());
+
+        QueryRows {
+            taken: changed.unwrap_or_default(),
+            rows,
+        }
+    }
+
+    /// Narrows `rows` of `at`, or every row if `None`, to those whose entity has none of the sparse
+    /// components excluded by the query `M`
+    fn filter_sparse<M: QueryModel>(
+        &self,
+        at: &Archetype,
+        rows: Option<Vec<usize>>,
+    ) -> Option<Vec<usize>> {
+        M::get_excluded().iter().fold(rows, |rows, &type_id| {
+            self.without_sparse(at, type_id, rows)
+        })
+    }
+
+    /// Narrows `rows` of `at`, or every row if `None`, to those whose entity has no sparse
+    /// component of `type_id`
+    fn without_sparse(
+        &self,
+        at: &Archetype,
+        type_id: TypeId,
+        rows: Option<Vec<usize>>,
+    ) -> Option<Vec<usize>> {
+        let Some(store) = self.sparse.get(&type_id) else {
+            return rows;
+        };
+        let guard: ReadGuard = store.inner();
+        if guard.is_empty() {
+            return rows;
+        }
+
+        let entities = at.entities();
+        let all: bool = rows.is_none();
+        let kept: Vec<usize> = rows
+            .unwrap_or_else(|| (0..at.len()).collect())
+            .into_iter()
+            .filter(|&row| guard.entity_row(entities[row]).is_none())
+            .collect();
+
+        (!all || kept.len() < at.len()).then_some(kept)
+    }
+
+    /// Runs a system over `rows` of `at`, or every row if `None`, flagging the `marked` types of
+    /// each processed row as changed and returning the number of rows processed
+    ///
+    /// Rows in `taken` which are not processed are flagged again for the query's `Changed` filter,
+    /// so runners visiting only some rows do not lose changes.
+    fn process_query_rows<M, F>(
+        at: &Archetype,
+        marked: &TypeBundle,
+        taken: &[usize],
+        rows: Option<&[usize]>,
+        system: &mut F,
+    ) -> usize
+    where
+        M: QueryModel,
+        for<'m> F: FnMut(M::Row<'m>),
+    {
+        let Some(rows) = rows else {
+            let processed: usize = M::process(M::get_reads(at), M::get_writes(at), system);
+            at.mark_all_changed(marked);
+            return processed;
+        };
+
+        if !taken.is_empty() {
+            let mut visited: Vec<bool> = vec![false; at.len()];
+            rows.iter().for_each(|&row| visited[row] = true);
+            let skipped: Vec<usize> = taken.iter().copied().filter(|&row| !visited[row]).collect();
+            at.mark_changed(&M::get_changed_types(), &skipped);
+        }
+        if rows.is_empty() {
+            return 0;
+        }
+
+        let processed: usize = M::process_rows(M::get_reads(at), M::get_writes(at), rows, system);
+        at.mark_changed(marked, rows);
+
+        processed
+    }
+
+    /// Gets the types a query flags as changed, skipping those it filters on so systems do not
+    /// retrigger themselves
+    fn get_marked_types<M: QueryModel>() -> TypeBundle {
+        let changed_types: TypeBundle = M::get_changed_types();
+        let marked: Vec<TypeId> = M::get_write_types()
+            .iter()
+            .filter(|type_id| !changed_types.iter().any(|changed| changed == *type_id))
+            .copied()
+            .collect();
+
+        TypeBundle::from(marked.as_slice())
+    }
+
+    fn get_archetype_id(&self, types: &TypeBundle) -> Option<usize> {
+        self.index.get(types).copied()
+    }
+
+    /// Gets the index of the archetype holding exactly `types`, pushing an empty copy of the
+    /// layout of `template` if none is indexed
+    fn get_or_create_archetype_for(&mut self, types: TypeBundle, template: usize) -> usize {
+        if let Some(archetype_id) = self.get_archetype_id(&types) {
+            return archetype_id;
+        }
+
+        let archetype_id: usize = self.archetypes.len();
+        self.archetypes.push(self.archetypes[template].duplicate());
+        self.index.insert(types.clone(), archetype_id);
+        self.update_inclusive_index(types, archetype_id);
+
+        archetype_id
+    }
+
+    fn push_archetype(&mut self, bundle: ComponentBundle, entity: EntityId) -> usize {
+        bundle.iter_boxes().for_each(ComponentBox::register_name);
+        let types: TypeBundle = bundle.types();
+        let archetype_id: usize = self.archetypes.len();
+        self.index.insert(types.clone(), archetype_id);
+        self.archetypes.push(Archetype::with_capacity(
+            bundle,
+            entity,
+            self.archetype_capacity,
+        ));
+        self.update_inclusive_index(types, archetype_id);
+
+        archetype_id
+    }
+
+    /// Indexes a newly pushed archetype, caching its `types` as an inclusive query
+    ///
+    /// Cached queries are listed under their first type, so only those sharing a type with the new
+    /// archetype are checked, each exactly once.
+    fn update_inclusive_index(&mut self, types: TypeBundle, archetype_id: usize) {
+        for &type_id in types.iter() {
+            self.archetypes_by_type
+                .entry(type_id)
+                .or_default()
+                .push(archetype_id);
+        }
+
+        let mut matched: Vec<TypeBundle> = types
+            .iter()
+            .filter_map(|type_id| self.queries_by_type.get(type_id))
+            .flatten()
+            .filter(|query| types.is_superset_of(query))
+            .cloned()
+            .collect();
+        // the empty query has no first type, but matches every archetype
+        matched.push(TypeBundle::default());
+        for query in matched.iter() {
+            if let Some(ids) = self.inclusive_index.get_mut(query) {
+                ids.push(archetype_id);
+            }
+        }
+
+        self.cache_inclusive_query(types);
+    }
+
+    /// Caches the archetypes containing `types`, keeping the entry current as archetypes are pushed
+    fn cache_inclusive_query(&mut self, types: TypeBundle) {
+        if self.inclusive_index.contains_key(&types) {
+            return;
+        }
+
+        let ids: Vec<usize> = self.find_archetypes_inclusive(&types);
+        if let Some(&first) = types.iter().next() {
+            self.queries_by_type
+                .entry(first)
+                .or_default()
+                .push(types.clone());
+        }
+        self.inclusive_index.insert(types, ids);
+    }
+
+    /// Borrows two distinct archetypes mutably, in the order given
+    ///
+    /// Returns `None` if `first` and `second` are the same archetype.
+    fn mutate_archetypes(
+        &mut self,
+        first: usize,
+        second: usize,
+    ) -> Option<(&mut Archetype, &mut Archetype)> {
+        match first.cmp(&second) {
+            Ordering::Less => {
+                let (a, b) = self.archetypes.split_at_mut(second);
+                Some((&mut a[first], &mut b[0]))
+            }
+            Ordering::Greater => {
+                let (a, b) = self.archetypes.split_at_mut(first);
+                Some((&mut b[0], &mut a[second]))
+            }
+            Ordering::Equal => None,
+        }
+    }
+
+    fn check_system(&self, system: SystemId) -> Result<(), EcsError> {
+        if system.0 < self.systems.len() {
+            Ok(())
+        } else {
+            Err(EcsError::SystemNotFound)
+        }
+    }
+
+    /// Declares that `first` runs before `second`, rolling back the latest registration on a cycle
+    fn add_system_order(&mut self, first: SystemId, second: SystemId) -> Result<(), EcsError> {
+        self.system_order.push((first, second));
+        if let Err(err) = self.sort_systems() {
+            self.system_order.pop();
+            self.systems.pop();
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Topologically sorts registered systems, breaking ties by registration order
+    fn sort_systems(&self) -> Result<Vec<usize>, EcsError> {
+        let mut incoming: Vec<usize> = vec![0; self.systems.len()];
+        for (_, second) in self.system_order.iter() {
+            incoming[second.0] += 1;
+        }
+
+        let mut order: Vec<usize> = Vec::with_capacity(self.systems.len());
+        let mut visited: Vec<bool> = vec![false; self.systems.len()];
+        while order.len() < self.systems.len() {
+            let next: usize = (0..self.systems.len())
+                .find(|&idx| !visited[idx] && incoming[idx] == 0)
+                .ok_or(EcsError::SystemCyclicDependency)?;
+            visited[next] = true;
+            order.push(next);
+            for (first, second) in self.system_order.iter() {
+                if first.0 == next {
+                    incoming[second.0] -= 1;
+                }
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::init()
+    }
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("World")
+            .field("archetypes", &self.archetype_count())
+            .field("entities", &self.entity_count())
+            .field("resources", &self.resources.len())
+            .finish()
+    }
+}
+
+/// Defines the `QueryRows` of an archetype. The rows passing the filters of a query
+struct QueryRows {
+    /// Rows passing every filter, or `None` if every row passes
+    rows: Option<Vec<usize>>,
+    /// Rows whose changed flags were taken by the query's `Changed` filter
+    taken: Vec<usize>,
+}
+
+impl QueryRows {
+    /// Gets every passing row of `at`
+    fn selected(&self, at: &Archetype) -> Vec<usize> {
+        self.rows.clone().unwrap_or_else(|| (0..at.len()).collect())
+    }
+}
+
+/// Defines an `EventQueue`. Buffers the events of one type sent over the last two frames
+///
+/// Events before `current` were sent during the previous frame.
+struct EventQueue<T> {
+    events: Vec<T>,
+    current: usize,
+}
+
+impl<T> Default for EventQueue<T> {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            current: 0,
+        }
+    }
+}
+
+/// Type-erased access to an `EventQueue`, letting the world advance every queue at once
+trait AnyEventQueue: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn next_frame(&mut self);
+    fn clear(&mut self);
+}
+
+impl<T: Send + Sync + 'static> AnyEventQueue for EventQueue<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn next_frame(&mut self) {
+        self.events.drain(..self.current);
+        self.current = self.events.len();
+    }
+
+    fn clear(&mut self) {
+        self.events.clear();
+        self.current = 0;
+    }
+}
+
+/// Defines a `SystemScheduler`. Runs named systems against a `World` in registration order
+///
+/// Unlike systems added with `World::add_system`, scheduled systems live outside the world and can
+/// be paused and resumed by name.
+#[derive(Default)]
+pub struct SystemScheduler {
+    systems: Vec<ScheduledSystem>,
+}
+
+struct ScheduledSystem {
+    name: &'static str,
+    paused: bool,
+    system: BoxedSystem,
+}
+
+impl SystemScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a named system, failing if the name is already taken
+    pub fn register<M, F>(&mut self, name: &'static str, mut system: F) -> Result<(), EcsError>
+    where
+        M: QueryModel + 'static,
+        for<'m> F: FnMut(M::Row<'m>) + 'static,
+    {
+        if self.systems.iter().any(|scheduled| scheduled.name == name) {
+            return Err(EcsError::DuplicateSystemName);
+        }
+
+        self.systems.push(ScheduledSystem {
+            name,
+            paused: false,
+            system: Box::new(move |world: &mut World| {
+                world.run_system::<M, F>(&mut system);
+            }),
+        });
+
+        Ok(())
+    }
+
+    /// Stops the named system from running until it is resumed
+    pub fn pause(&mut self, name: &str) -> Result<(), EcsError> {
+        self.get_system_mut(name)?.paused = true;
+
+        Ok(())
+    }
+
+    pub fn resume(&mut self, name: &str) -> Result<(), EcsError> {
+        self.get_system_mut(name)?.paused = false;
+
+        Ok(())
+    }
+
+    pub fn is_paused(&self, name: &str) -> Result<bool, EcsError> {
+        self.systems
+            .iter()
+            .find(|scheduled| scheduled.name == name)
+            .map(|scheduled| scheduled.paused)
+            .ok_or(EcsError::SystemNotFound)
+    }
+
+    /// Runs every system that is not paused once, in registration order
+    pub fn run_all(&mut self, world: &mut World) {
+        for scheduled in self
+            .systems
+            .iter_mut()
+            .filter(|scheduled| !scheduled.paused)
+        {
+            (scheduled.system)(world);
+        }
+    }
+
+    fn get_system_mut(&mut self, name: &str) -> Result<&mut ScheduledSystem, EcsError> {
+        self.systems
+            .iter_mut()
+            .find(|scheduled| scheduled.name == name)
+            .ok_or(EcsError::SystemNotFound)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl World {
+    /// Registers a `Component` type to be included in snapshots
+    pub fn register_component_serde<T>(&mut self)
+    where
+        T: Component + serde::Serialize + serde::de::DeserializeOwned,
+    {
+        T::register_name();
+        self.component_serde
+            .insert(TypeId::of::<T>(), ComponentSerde::of::<T>());
+    }
+
+    /// Registers a resource type to be included in snapshots
+    pub fn register_resource_serde<T>(&mut self)
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static,
+    {
+        self.resource_serde
+            .insert(TypeId::of::<T>(), ResourceSerde::of::<T>());
+    }
+
+    /// Captures every archetype, entity location, and resource in the world
+    ///
+    /// Fails if any stored component or resource type has not been registered for serde, or if any
+    /// sparse component is stored.
+    pub fn snapshot(&self) -> Result<WorldSnapshot, EcsError> {
+        if self.sparse.values().any(|store| !store.is_empty()) {
+            return Err(StoreError::WrongStorageKind.into());
+        }
+        let archetypes: Vec<ArchetypeSnapshot> = self
+            .archetypes
+            .iter()
+            .map(|at| {
+                let components: HashMap<String, serde_json::Value> = at
+                    .types_ref()
+                    .iter()
+                    .map(|type_id| {
+                        let serde: &ComponentSerde = self
+                            .component_serde
+                            .get(type_id)
+                            .ok_or(StoreError::TypeNotRegistered)?;
+                        let store: &ComponentStore = at
+                            .get_storage(*type_id)
+                            .ok_or(StoreError::StorageNotFound)?;
+                        Ok((serde.name.to_string(), (serde.serialize)(store)?))
+                    })
+                    .collect::<Result<_, EcsError>>()?;
+
+                Ok(ArchetypeSnapshot {
+                    entities: at.entities().clone(),
+                    components,
+                })
+            })
+            .collect::<Result<_, EcsError>>()?;
+        let resources: HashMap<String, serde_json::Value> = self
+            .resources
+            .iter()
+            .map(|(type_id, resource)| {
+                let serde: &ResourceSerde = self
+                    .resource_serde
+                    .get(type_id)
+                    .ok_or(StoreError::TypeNotRegistered)?;
+                Ok((
+                    serde.name.to_string(),
+                    (serde.serialize)(resource.as_ref())?,
+                ))
+            })
+            .collect::<Result<_, EcsError>>()?;
+
+        Ok(WorldSnapshot {
+            entities: self.entities.clone(),
+            archetypes,
+            resources,
+        })
+    }
+
+    /// Rebuilds the world from `snapshot`, replacing every archetype, entity, and resource
+    ///
+    /// Sparse components and pending events belong to the replaced entities and frames, so both are
+    /// dropped. Registrations and systems are kept, so restoring is typically done on a freshly initialised
+    /// world with the snapshot's types registered. Fails if any type in the snapshot has not been
+    /// registered for serde.
+    pub fn restore(mut self, snapshot: WorldSnapshot) -> Result<World, EcsError> {
+        let component_serde: HashMap<&str, &ComponentSerde> = self
+            .component_serde
+            .values()
+            .map(|serde| (serde.name, serde))
+            .collect();
+        let archetypes: Vec<Archetype> = snapshot
+            .archetypes
+            .into_iter()
+            .map(|at| {
+                let stores: Vec<ComponentStore> = at
+                    .components
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let serde: &ComponentSerde = component_serde
+                            .get(name.as_str())
+                            .ok_or(StoreError::TypeNotRegistered)?;
+                        Ok((serde.deserialize)(value)?)
+                    })
+                    .collect::<Result<_, EcsError>>()?;
+
+                Ok(Archetype::from_stores(stores, at.entities)?)
+            })
+            .collect::<Result<_, EcsError>>()?;
+        if !archetypes
+            .first()
+            .is_some_and(|at| *at.types_ref() == TypeBundle::default())
+        {
+            return Err(EcsError::ArchetypeNotFound);
+        }
+
+        let resource_serde: HashMap<&str, (TypeId, &ResourceSerde)> = self
+            .resource_serde
+            .iter()
+            .map(|(&type_id, serde)| (serde.name, (type_id, serde)))
+            .collect();
+        let resources: HashMap<TypeId, Box<dyn Any + Send + Sync>> = snapshot
+            .resources
+            .into_iter()
+            .map(|(name, value)| {
+                let (type_id, serde) = resource_serde
+                    .get(name.as_str())
+                    .ok_or(StoreError::TypeNotRegistered)?;
+                Ok((*type_id, (serde.deserialize)(value)?))
+            })
+            .collect::<Result<_, EcsError>>()?;
+
+        // duplicated archetypes share types, so the first keeps the index entry
+        self.index.clear();
+        for (idx, at) in archetypes.iter().enumerate() {
+            self.index.entry(at.types()).or_insert(idx);
+        }
+        self.archetypes = archetypes;
+        self.entities = snapshot.entities;
+        self.inclusive_index.clear();
+        self.queries_by_type.clear();
+        self.archetypes_by_type.clear();
+        for (idx, at) in self.archetypes.iter().enumerate() {
+            for &type_id in at.types_ref().iter() {
+                self.archetypes_by_type
+                    .entry(type_id)
+                    .or_default()
+                    .push(idx);
+            }
+        }
+        self.resources = resources;
+        self.sparse.clear();
+        self.events.clear();
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as ecs;
+    use crate::test_utils::*;
+    use ecs_derive::QueryModel;
+
+    #[derive(QueryModel)]
+    struct ReadsA<'a> {
+        comp_a: &'a TestCompA,
+    }
+
+    #[test]
+    fn test_register_query() {
+        let mut world: World = World::init();
+        let types: TypeBundle = ReadsA::get_types();
+
+        world.register_query::<ReadsA>();
+        assert!(world.inclusive_index[&types].is_empty());
+
+        world
+            .spawn(
+                ComponentBundle::default()
+                    .insert(TestCompA::default())
+                    .insert(TestCompB::default()),
+            )
+            .unwrap();
+        world.spawn_one(TestCompB::default()).unwrap();
+        world.spawn_one(TestCompA::default()).unwrap();
+
+        assert!(world.inclusive_index[&types] == vec![1, 3]);
+        assert!(world.run_system::<ReadsA, _>(&mut |row| assert!(row.comp_a.one == 0)) == 2);
+        world.register_query::<ReadsA>();
+        assert!(world.inclusive_index[&types] == vec![1, 3]);
+    }
+
+    #[test]
+    fn test_default_is_empty() {
+        let world: World = World::default();
+
+        assert!(world.entity_count() == 0);
+        assert!(world.archetype_count() == World::init().archetype_count());
+        world.validate();
+    }
+
+    #[test]
+    fn test_init_with_capacity() {
+        let mut world: World = World::init_with_capacity(32);
+        world
+            .spawn(ComponentBundle::default().insert(TestCompA::default()))
+            .unwrap();
+
+        let at: &Archetype = world.get_archetype(1).unwrap();
+        assert!(at.entities().capacity() >= 32);
+    }
+
+    #[test]
+    fn test_despawn_all() {
+        let mut world: World = World::init();
+
+        let entities: Vec<EntityId> = (0..3)
+            .map(|one| {
+                let bundle: ComponentBundle = ComponentBundle::default()
+                    .insert(TestCompA {
+                        one,
+                        ..Default::default()
+                    })
+                    .insert(TestCompB::default());
+                world.spawn(bundle).unwrap()
+            })
+            .collect();
+
+        world.despawn_all();
+
+        for entity in entities.into_iter() {
+            assert!(matches!(
+                world.entities.entity_status(entity),
+                Err(EntityError::NotFound)
+            ));
+        }
+        assert!(world.archetypes.len() == 1);
+        assert!(world.get_component_count::<TestCompA>() == 0);
+
+        world
+            .spawn(ComponentBundle::default().insert(TestCompA::default()))
+            .unwrap();
+        assert!(world.get_component_count::<TestCompA>() == 1);
     }
 }
@@ -0,0 +1,145 @@
+mod utils;
+use utils::*;
+
+use ecs::{
+    bundle::{ComponentBundle, TypeBundle},
+    component::ComponentBox,
+    query::QueryModel as _,
+    world::World,
+};
+use ecs_derive::{Bundle, Component, QueryModel};
+
+#[derive(Component, Debug, PartialEq)]
+#[component(allow_drop)]
+struct Quiet(u32);
+
+impl Drop for Quiet {
+    fn drop(&mut self) {}
+}
+
+#[test]
+fn test_derive_component_allow_drop() {
+    let comp: ComponentBox = Quiet(3).into();
+
+    assert!(comp.cast_inner::<Quiet>().unwrap() == Quiet(3));
+}
+
+#[test]
+fn test_derive_compile_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+    cases.pass("tests/ui/pass/*.rs");
+}
+
+#[derive(QueryModel)]
+struct NamedLifetimeQuery<'world> {
+    pos: &'world mut Position,
+    health: &'world Health,
+}
+
+#[test]
+fn test_derive_query_model_named_lifetime() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(Position::default())
+        .insert(Health(2));
+    world.spawn(bundle).unwrap();
+
+    world.run_system::<NamedLifetimeQuery, _>(&mut |row: NamedLifetimeQuery| {
+        row.pos.x += row.health.0;
+    });
+
+    let positions: Vec<i32> =
+        world.run_system_collecting::<NamedLifetimeQuery, _, _>(&mut |row: NamedLifetimeQuery| {
+            row.pos.x
+        });
+    assert!(positions == vec![2]);
+}
+
+#[derive(QueryModel)]
+struct PositionalQuery<'a>(&'a mut Position, &'a Health);
+
+#[derive(QueryModel)]
+struct EmptyQuery;
+
+#[test]
+fn test_derive_query_model_tuple_and_unit() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(Position::default())
+        .insert(Health(3));
+    world.spawn(bundle).unwrap();
+
+    world.run_system::<PositionalQuery, _>(&mut |row: PositionalQuery| {
+        row.0.y += row.1 .0;
+    });
+
+    let positions: Vec<i32> =
+        world.run_system_collecting::<PositionalQuery, _, _>(&mut |row: PositionalQuery| row.0.y);
+    assert!(positions == vec![3]);
+
+    assert!(EmptyQuery::get_types() == TypeBundle::default());
+    let mut calls: usize = 0;
+    world.run_system::<EmptyQuery, _>(&mut |_: EmptyQuery| calls += 1);
+    assert!(calls == 0);
+}
+
+#[test]
+fn test_derive_component_clone_box() {
+    let cloneable: ComponentBox = Health(3).into();
+    let unique: ComponentBox = Quiet(3).into();
+
+    assert!(
+        cloneable
+            .try_clone()
+            .unwrap()
+            .cast_inner::<Health>()
+            .unwrap()
+            == Health(3)
+    );
+    assert!(unique.try_clone().is_none());
+
+    let bundle: ComponentBundle = ComponentBundle::default().insert(Quiet(1));
+    assert!(bundle.try_clone().is_none());
+}
+
+#[derive(Bundle)]
+struct EnemyTemplate {
+    health: Health,
+    pos: Position,
+}
+
+#[derive(Bundle)]
+struct TaggedTemplate(Health, Frozen);
+
+#[test]
+fn test_derive_bundle() {
+    let mut world: World = World::init();
+
+    let enemy = EnemyTemplate {
+        health: Health(12),
+        pos: Position { x: 1, y: 2 },
+    }
+    .spawn(&mut world)
+    .unwrap();
+    let tagged = world
+        .spawn(TaggedTemplate(Health(3), Frozen).into())
+        .unwrap();
+
+    assert!(*world.get_component::<Health>(enemy).unwrap() == Health(12));
+    assert!(*world.get_component::<Position>(enemy).unwrap() == Position { x: 1, y: 2 });
+    assert!(*world.get_component::<Health>(tagged).unwrap() == Health(3));
+    assert!(world.has_component::<Frozen>(tagged).unwrap());
+}
+
+#[test]
+#[should_panic(expected = "Archetype missing component Position")]
+fn test_derive_query_model_missing_storage_message() {
+    let mut world: World = World::init();
+    let entity = world.spawn_one(Health(1)).unwrap();
+
+    let at = world.get_archetype_for_entity(entity).unwrap();
+    let _ = NamedLifetimeQuery::get_writes(at);
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "serde")]
+
+use ecs::{
+    bundle::ComponentBundle,
+    entity::EntityId,
+    errors::{EcsError, StoreError},
+    snapshot::WorldSnapshot,
+    world::World,
+};
+use ecs_derive::Component;
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Serialize, Deserialize, PartialEq, Debug, Clone)]
+struct Health(i32);
+
+#[derive(Component, Serialize, Deserialize, PartialEq, Debug, Clone)]
+struct Name(String);
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct DeltaTime(f32);
+
+#[derive(Component, PartialEq, Debug)]
+#[component(storage = "sparse")]
+struct Marked;
+
+fn registered_world() -> World {
+    let mut world: World = World::init();
+    world.register_component_serde::<Health>();
+    world.register_component_serde::<Name>();
+    world.register_resource_serde::<DeltaTime>();
+    world
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let mut world: World = registered_world();
+    let entities: Vec<EntityId> = (0..4)
+        .map(|health| {
+            let mut bundle: ComponentBundle = ComponentBundle::default().insert(Health(health));
+            if health % 2 == 0 {
+                bundle = bundle.insert(Name(format!("entity {health}")));
+            }
+            world.spawn(bundle).unwrap()
+        })
+        .collect();
+    world.kill(entities[1]).unwrap();
+    world.insert_resource(DeltaTime(0.25));
+
+    let json: String = serde_json::to_string(&world.snapshot().unwrap()).unwrap();
+    let snapshot: WorldSnapshot = serde_json::from_str(&json).unwrap();
+    let restored: World = registered_world().restore(snapshot).unwrap();
+
+    restored.validate();
+    assert!(restored.entity_count() == 3);
+    assert!(restored.resource::<DeltaTime>() == Some(&DeltaTime(0.25)));
+    assert!(restored.get_component::<Health>(entities[1]).is_none());
+    for &idx in [0, 2, 3].iter() {
+        let entity: EntityId = entities[idx];
+        assert!(*restored.get_component::<Health>(entity).unwrap() == Health(idx as i32));
+        assert!(
+            restored
+                .get_component::<Name>(entity)
+                .map(|name| name.clone())
+                == (idx % 2 == 0).then(|| Name(format!("entity {idx}")))
+        );
+    }
+}
+
+#[test]
+fn test_snapshot_unregistered_component() {
+    let mut world: World = World::init();
+    world.register_component_serde::<Health>();
+    world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(1))
+                .insert(Name("unregistered".to_string())),
+        )
+        .unwrap();
+
+    assert!(matches!(
+        world.snapshot(),
+        Err(EcsError::Store(StoreError::TypeNotRegistered))
+    ));
+}
+
+#[test]
+fn test_restore_unregistered_component() {
+    let mut world: World = registered_world();
+    world
+        .spawn(ComponentBundle::default().insert(Name("named".to_string())))
+        .unwrap();
+    let snapshot: WorldSnapshot = world.snapshot().unwrap();
+
+    let mut restoring: World = World::init();
+    restoring.register_component_serde::<Health>();
+
+    assert!(matches!(
+        restoring.restore(snapshot),
+        Err(EcsError::Store(StoreError::TypeNotRegistered))
+    ));
+}
+
+#[test]
+fn test_restore_drops_sparse_components_and_events() {
+    let mut world: World = registered_world();
+    let entity: EntityId = world.spawn_one(Health(1)).unwrap();
+    let snapshot: WorldSnapshot = world.snapshot().unwrap();
+
+    let mut restoring: World = registered_world();
+    let stale: EntityId = restoring
+        .spawn(ComponentBundle::default().insert(Health(2)).insert(Marked))
+        .unwrap();
+    restoring.send_event(DeltaTime(0.5));
+    let restored: World = restoring.restore(snapshot).unwrap();
+
+    assert!(stale == entity);
+    assert!(!restored.has_component::<Marked>(entity).unwrap());
+    assert!(restored.get_component_count::<Marked>() == 0);
+    assert!(restored.read_events::<DeltaTime>().is_empty());
+    assert!(restored.snapshot().is_ok());
+}
@@ -1,8 +1,21 @@
 mod utils;
 use utils::*;
 
-use ecs::{archetype::Migration, bundle::ComponentBundle, world::World};
+use ecs::{
+    archetype::{Archetype, Migration},
+    bundle::{ComponentBundle, Prefab, TypeBundle},
+    entity::EntityId,
+    errors::{EcsError, EntityError, StoreError},
+    hierarchy::Children,
+    query::{Changed, Without},
+    world::{SystemScheduler, World, WorldCommand},
+};
 use ecs_derive::QueryModel;
+use std::{
+    any::TypeId,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 #[derive(QueryModel)]
 pub struct TestDataA<'a> {
@@ -13,7 +26,7 @@ pub struct TestDataA<'a> {
 
 fn test_system(row: TestDataA) {
     let a: &TestCompA = row.comp_a;
-    let mut b: &mut TestCompB = row.comp_b;
+    let b: &mut TestCompB = row.comp_b;
     let c: &TestCompC = row.comp_c;
 
     println!("{:?}, {:?}, {:?}", a, b, c);
@@ -35,8 +48,2210 @@ fn test_world() {
     let entity_a = world.spawn(bundle).unwrap();
 
     world
-        .migrate(entity_a, Migration::Add(TestCompB::default().into()))
+        .migrate(entity_a, Migration::Add1(TestCompB::default().into()))
         .unwrap();
 
     world.run_system::<TestDataA, _>(&mut test_system);
 }
+
+#[derive(QueryModel)]
+pub struct TestWithoutB<'a> {
+    comp_a: &'a mut TestCompA,
+    _without_b: Without<TestCompB>,
+}
+
+#[derive(QueryModel)]
+pub struct TestChangedPosition<'a> {
+    pos: Changed<&'a Position>,
+}
+
+#[derive(QueryModel)]
+pub struct TestMovePosition<'a> {
+    pos: &'a mut Position,
+}
+
+#[derive(QueryModel)]
+pub struct TestChangedHealth<'a> {
+    health: Changed<&'a mut Health>,
+}
+
+#[derive(QueryModel)]
+pub struct TestReadChangedHealth<'a> {
+    health: Changed<&'a Health>,
+}
+
+#[derive(QueryModel)]
+pub struct TestWithoutRare<'a> {
+    health: &'a mut Health,
+    _without_rare: Without<Rare>,
+}
+
+#[derive(QueryModel)]
+pub struct TestReadOnly<'a> {
+    comp_a: &'a TestCompA,
+    comp_c: &'a TestCompC,
+}
+
+#[test]
+fn test_world_read_only_query() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA {
+            one: 1,
+            two: "one".to_string(),
+        })
+        .insert(TestCompC::default());
+
+    world.spawn(bundle).unwrap();
+
+    let mut seen: Vec<u32> = Vec::new();
+    world.run_system::<TestReadOnly, _>(&mut |row: TestReadOnly| {
+        assert!(row.comp_c == &TestCompC::default());
+        seen.push(row.comp_a.one);
+    });
+
+    assert!(seen == vec![1]);
+}
+
+#[test]
+fn test_world_spawn_and_get() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA::default())
+        .insert(Health(42));
+
+    let (_, health) = world.spawn_and_get::<Health>(bundle).unwrap();
+    assert!(health == Health(42));
+
+    let missing: ComponentBundle = ComponentBundle::default().insert(TestCompA::default());
+    assert!(world.spawn_and_get::<Health>(missing).is_err());
+}
+
+#[test]
+fn test_world_duplicate_archetype() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA::default())
+        .insert(TestCompB::default())
+        .insert(TestCompC::default());
+
+    world.spawn(bundle).unwrap();
+    let archetypes: usize = world.archetype_count();
+
+    // one archetype per `TypeBundle`, so the layout resolves to the original
+    let copy: usize = world.duplicate_archetype(1).unwrap();
+    assert!(copy == 1);
+    assert!(world.archetype_count() == archetypes);
+    assert!(world.get_archetype(copy).unwrap().entities().len() == 1);
+    assert!(world.duplicate_archetype(0).unwrap() == 0);
+
+    assert!(matches!(
+        world.duplicate_archetype(100),
+        Err(EcsError::ArchetypeNotFound)
+    ));
+}
+
+#[test]
+fn test_world_system_ordering() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA::default())
+        .insert(TestCompC::default());
+
+    world.spawn(bundle).unwrap();
+
+    let calls: Arc<Mutex<Vec<&str>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let log: Arc<Mutex<Vec<&str>>> = calls.clone();
+    let system_c = world.add_system::<TestReadOnly, _>(move |_: TestReadOnly| {
+        log.lock().unwrap().push("c");
+    });
+    let log: Arc<Mutex<Vec<&str>>> = calls.clone();
+    let system_b = world
+        .add_system_before::<TestReadOnly, _>(
+            move |_: TestReadOnly| log.lock().unwrap().push("b"),
+            system_c,
+        )
+        .unwrap();
+    let log: Arc<Mutex<Vec<&str>>> = calls.clone();
+    world
+        .add_system_before::<TestReadOnly, _>(
+            move |_: TestReadOnly| log.lock().unwrap().push("a"),
+            system_b,
+        )
+        .unwrap();
+    let log: Arc<Mutex<Vec<&str>>> = calls.clone();
+    world
+        .add_system_after::<TestReadOnly, _>(
+            move |_: TestReadOnly| log.lock().unwrap().push("d"),
+            system_c,
+        )
+        .unwrap();
+
+    world.run_systems();
+
+    assert!(*calls.lock().unwrap() == vec!["a", "b", "c", "d"]);
+}
+
+#[test]
+fn test_world_spawn_one() {
+    let mut world: World = World::init();
+
+    let entity: EntityId = world.spawn_one(Health(5)).unwrap();
+    let other: EntityId = world.spawn(Health(6).into()).unwrap();
+
+    world.validate();
+    assert!(*world.get_component::<Health>(entity).unwrap() == Health(5));
+    assert!(*world.get_component::<Health>(other).unwrap() == Health(6));
+    assert!(world.archetype_count() == 2);
+}
+
+#[test]
+fn test_system_scheduler() {
+    let mut world: World = World::init();
+    world
+        .spawn(
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompC::default()),
+        )
+        .unwrap();
+
+    let calls: Arc<Mutex<Vec<&str>>> = Arc::new(Mutex::new(Vec::new()));
+    let mut scheduler: SystemScheduler = SystemScheduler::new();
+    for name in ["physics", "render"] {
+        let log: Arc<Mutex<Vec<&str>>> = calls.clone();
+        scheduler
+            .register::<TestReadOnly, _>(name, move |_: TestReadOnly| {
+                log.lock().unwrap().push(name)
+            })
+            .unwrap();
+    }
+    assert!(matches!(
+        scheduler.register::<TestReadOnly, _>("render", |_: TestReadOnly| {}),
+        Err(EcsError::DuplicateSystemName)
+    ));
+
+    scheduler.run_all(&mut world);
+    scheduler.pause("physics").unwrap();
+    assert!(scheduler.is_paused("physics").unwrap());
+    scheduler.run_all(&mut world);
+    scheduler.resume("physics").unwrap();
+    scheduler.run_all(&mut world);
+
+    assert!(*calls.lock().unwrap() == vec!["physics", "render", "render", "physics", "render"]);
+    assert!(matches!(
+        scheduler.pause("missing"),
+        Err(EcsError::SystemNotFound)
+    ));
+}
+
+#[test]
+fn test_world_get_archetype_for_entity_ref() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA::default())
+        .insert(TestCompB::default());
+    let types: TypeBundle = bundle.types();
+
+    let entity = world.spawn(bundle).unwrap();
+    let archetype: &Archetype = world.get_archetype_for_entity_ref(entity).unwrap();
+
+    assert!(archetype.len() == 1);
+    assert!(archetype.types() == types);
+
+    world.kill(entity).unwrap();
+    assert!(matches!(
+        world.get_archetype_for_entity_ref(entity),
+        Err(EcsError::Entity(
+            EntityError::NotFound | EntityError::WrongGen
+        ))
+    ));
+}
+
+#[test]
+fn test_world_retain() {
+    #[derive(QueryModel)]
+    struct HealthQuery<'a> {
+        health: &'a Health,
+    }
+
+    let mut world: World = World::init();
+    let entities: Vec<EntityId> = (0..6)
+        .map(|health| {
+            let bundle: ComponentBundle = ComponentBundle::default().insert(Health(health % 3));
+            if health < 3 {
+                world.spawn(bundle).unwrap()
+            } else {
+                world.spawn(bundle.insert(Frozen)).unwrap()
+            }
+        })
+        .collect();
+
+    let killed: usize = world
+        .retain::<HealthQuery, _>(|row: HealthQuery| row.health.0 > 0)
+        .unwrap();
+
+    world.validate();
+    assert!(killed == 2);
+    assert!(world.entity_count() == 4);
+    for (idx, &entity) in entities.iter().enumerate() {
+        assert!(world.has_component::<Health>(entity).is_ok() == (idx % 3 != 0));
+    }
+}
+
+#[test]
+fn test_world_take_components() {
+    let mut world: World = World::init();
+
+    let frozen: Vec<EntityId> = (0..3)
+        .map(|health| {
+            world
+                .spawn(
+                    ComponentBundle::default()
+                        .insert(Health(health))
+                        .insert(Frozen),
+                )
+                .unwrap()
+        })
+        .collect();
+    let positioned: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(3))
+                .insert(Position::default())
+                .insert(Frozen),
+        )
+        .unwrap();
+    let untouched: EntityId = world.spawn_one(Health(4)).unwrap();
+
+    let taken: Vec<(Frozen,)> = world.take_components::<(Frozen,)>().unwrap();
+    world.validate();
+    assert!(taken.len() == 4);
+    assert!(world.get_component_count::<Frozen>() == 0);
+    assert!(world.entity_count() == 5);
+    for (health, &entity) in frozen
+        .iter()
+        .chain([positioned, untouched].iter())
+        .enumerate()
+    {
+        assert!(*world.get_component::<Health>(entity).unwrap() == Health(health as i32));
+    }
+    assert!(world.has_component::<Position>(positioned).unwrap());
+
+    let mut taken: Vec<(Position, Health)> = world.take_components::<(Position, Health)>().unwrap();
+    world.validate();
+    assert!(taken.pop().unwrap().1 == Health(3));
+    assert!(taken.is_empty());
+    assert!(!world.has_component::<Health>(positioned).unwrap());
+}
+
+#[test]
+fn test_world_compact_edges() {
+    let mut world: World = World::init();
+
+    let kept: EntityId = world.spawn_one(Health(0)).unwrap();
+    let removed: EntityId = world.spawn_one(Health(1)).unwrap();
+    world.migrate_add(removed, Frozen).unwrap();
+    let edges = |world: &World| -> usize {
+        (0..world.archetype_count())
+            .map(|idx| world.get_archetype(idx).unwrap().edge_count())
+            .sum()
+    };
+    assert!(edges(&world) == 2);
+
+    world.kill(removed).unwrap();
+    assert!(world.compact_edges() == 2);
+    assert!(edges(&world) == 0);
+    assert!(world.compact_edges() == 0);
+
+    let archetypes: usize = world.archetype_count();
+    let respawned: EntityId = world
+        .spawn(ComponentBundle::default().insert(Health(2)).insert(Frozen))
+        .unwrap();
+    world.validate();
+    assert!(world.archetype_count() == archetypes + 1);
+    assert!(world.has_component::<Frozen>(respawned).unwrap());
+    assert!(*world.get_component::<Health>(kept).unwrap() == Health(0));
+}
+
+#[test]
+fn test_world_compact_edges_reclaims_archetypes() {
+    let mut world: World = World::init();
+    let frozen: EntityId = world
+        .spawn(ComponentBundle::default().insert(Frozen))
+        .unwrap();
+    world.kill(frozen).unwrap();
+    let kept: EntityId = world.spawn_one(Health(0)).unwrap();
+    world.migrate_add(kept, Position::default()).unwrap();
+
+    world.compact_edges();
+    let archetypes: usize = world.archetype_count();
+    for cycle in 0..5 {
+        let temp: EntityId = world
+            .spawn(
+                ComponentBundle::default()
+                    .insert(Health(cycle))
+                    .insert(Frozen),
+            )
+            .unwrap();
+        world.kill(temp).unwrap();
+        world.compact_edges();
+        assert!(world.archetype_count() == archetypes);
+    }
+
+    world.validate();
+    assert!(world.archetype_count() == 2);
+    assert!(world
+        .get_archetype_for_entity(kept)
+        .unwrap()
+        .has_type(TypeId::of::<Position>()));
+    assert!(*world.get_component::<Health>(kept).unwrap() == Health(0));
+    assert!(
+        world.run_system::<TestMovePosition, _>(&mut |row: TestMovePosition| row.pos.x += 1) == 1
+    );
+    world.migrate_remove::<Position>(kept).unwrap();
+    assert!(world.has_component::<Health>(kept).unwrap());
+    assert!(world.archetype_count() == 3);
+}
+
+#[test]
+fn test_world_contains() {
+    let mut world: World = World::init();
+    let mut other: World = World::init();
+    let unallocated: EntityId = (0..3)
+        .map(|health| other.spawn_one(Health(health)).unwrap())
+        .last()
+        .unwrap();
+
+    let alive: EntityId = world.spawn_one(Health(2)).unwrap();
+    let dead: EntityId = world.spawn_one(Health(3)).unwrap();
+    world.kill(dead).unwrap();
+
+    assert!(world.contains(alive));
+    assert!(!world.contains(dead));
+    assert!(!world.contains(unallocated));
+
+    // the freed slot is reused by a new generation, which the stale id does not match
+    let reused: EntityId = world.spawn_one(Health(4)).unwrap();
+    assert!(world.contains(reused));
+    assert!(!world.contains(dead));
+}
+
+#[test]
+fn test_world_get_archetype_for_entity() {
+    let mut world: World = World::init();
+
+    let entity: EntityId = world.spawn_one(Health(3)).unwrap();
+    let archetype: &Archetype = world.get_archetype_for_entity(entity).unwrap();
+    assert!(archetype.types() == TypeBundle::from([TypeId::of::<Health>()].as_slice()));
+
+    world.kill(entity).unwrap();
+    assert!(world.get_archetype_for_entity(entity).is_none());
+}
+
+#[test]
+fn test_world_iter_entity_archetypes() {
+    let mut world: World = World::init();
+
+    let entity_a = world
+        .spawn(ComponentBundle::default().insert(TestCompA::default()))
+        .unwrap();
+    let entity_b = world
+        .spawn(ComponentBundle::default().insert(TestCompB::default()))
+        .unwrap();
+    let entity_c = world
+        .spawn(ComponentBundle::default().insert(TestCompA::default()))
+        .unwrap();
+
+    let pairs: Vec<_> = world.iter_entity_archetypes().collect();
+
+    assert!(pairs == vec![(entity_a, 1), (entity_b, 2), (entity_c, 1)]);
+}
+
+#[test]
+fn test_world_profile_run_system() {
+    let mut world: World = World::init();
+
+    for one in 0..3 {
+        let bundle: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompA {
+                one,
+                ..Default::default()
+            })
+            .insert(TestCompC::default());
+        world.spawn(bundle).unwrap();
+    }
+
+    let mut count: usize = 0;
+    let (elapsed, processed) =
+        world.profile_run_system::<TestReadOnly, _>(&mut |_: TestReadOnly| {
+            count += 1;
+        });
+
+    assert!(elapsed > Duration::ZERO);
+    assert!(count == 3);
+    assert!(processed == 3);
+
+    let (elapsed, collected) =
+        world.profile_run_system_collecting::<TestReadOnly, _, _>(&mut |row: TestReadOnly| {
+            row.comp_a.one
+        });
+
+    assert!(elapsed > Duration::ZERO);
+    assert!(collected == vec![0, 1, 2]);
+}
+
+#[test]
+fn test_world_run_system_returns_processed() {
+    let mut world: World = World::init();
+
+    assert!(world.run_system::<TestReadOnly, _>(&mut |_: TestReadOnly| {}) == 0);
+
+    for one in 0..2 {
+        world
+            .spawn(
+                ComponentBundle::default()
+                    .insert(TestCompA {
+                        one,
+                        ..Default::default()
+                    })
+                    .insert(TestCompC::default()),
+            )
+            .unwrap();
+    }
+    world
+        .spawn(
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompB::default())
+                .insert(TestCompC::default()),
+        )
+        .unwrap();
+
+    assert!(world.run_system::<TestReadOnly, _>(&mut |_: TestReadOnly| {}) == 3);
+}
+
+#[test]
+fn test_world_run_system_skips_empty_archetypes() {
+    let mut world: World = World::init();
+
+    let mut called: bool = false;
+    world.run_system::<TestReadOnly, _>(&mut |_: TestReadOnly| called = true);
+    assert!(!called);
+
+    let entity: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompC::default()),
+        )
+        .unwrap();
+    world.kill(entity).unwrap();
+
+    let processed: usize =
+        world.run_system::<TestReadOnly, _>(&mut |_: TestReadOnly| called = true);
+    assert!(!called);
+    assert!(processed == 0);
+}
+
+#[test]
+fn test_world_run_system_with_resource() {
+    #[derive(Default)]
+    struct Total(u32);
+
+    let mut world: World = World::init();
+    for one in 1..4 {
+        world
+            .spawn(
+                ComponentBundle::default()
+                    .insert(TestCompA {
+                        one,
+                        ..Default::default()
+                    })
+                    .insert(TestCompC::default()),
+            )
+            .unwrap();
+    }
+
+    let mut sum = |row: TestReadOnly, total: &mut Total| total.0 += row.comp_a.one;
+    assert!(matches!(
+        world.run_system_with_resource::<TestReadOnly, Total, _>(&mut sum),
+        Err(EcsError::ResourceNotFound)
+    ));
+
+    world.insert_resource(Total::default());
+    let processed: usize = world
+        .run_system_with_resource::<TestReadOnly, Total, _>(&mut sum)
+        .unwrap();
+
+    assert!(processed == 3);
+    assert!(world.resource::<Total>().unwrap().0 == 6);
+}
+
+#[test]
+fn test_world_query_single() {
+    let mut world: World = World::init();
+    let spawn_one = |world: &mut World, one: u32| {
+        world
+            .spawn(
+                ComponentBundle::default()
+                    .insert(TestCompA {
+                        one,
+                        ..Default::default()
+                    })
+                    .insert(TestCompC::default()),
+            )
+            .unwrap()
+    };
+
+    assert!(matches!(
+        world.query_single::<TestReadOnly, _, _>(|row: TestReadOnly| row.comp_a.one),
+        Err(EcsError::Entity(EntityError::NotFound))
+    ));
+
+    spawn_one(&mut world, 7);
+    assert!(
+        world
+            .query_single::<TestReadOnly, _, _>(|row: TestReadOnly| row.comp_a.one)
+            .unwrap()
+            == 7
+    );
+
+    spawn_one(&mut world, 8);
+    assert!(matches!(
+        world.query_single::<TestReadOnly, _, _>(|row: TestReadOnly| row.comp_a.one),
+        Err(EcsError::MultipleMatches)
+    ));
+    assert!(
+        world
+            .query_single_unchecked::<TestReadOnly, _, _>(|row: TestReadOnly| row.comp_a.one)
+            .unwrap()
+            == 7
+    );
+}
+
+#[test]
+fn test_world_resources() {
+    #[derive(Debug, PartialEq)]
+    struct DeltaTime(f32);
+
+    let mut world: World = World::init();
+    assert!(world.resource::<DeltaTime>().is_none());
+
+    world.insert_resource(DeltaTime(0.5));
+    assert!(world.resource::<DeltaTime>() == Some(&DeltaTime(0.5)));
+
+    world.resource_mut::<DeltaTime>().unwrap().0 = 1.0;
+    world.insert_resource(7_u64);
+    assert!(world.resource::<DeltaTime>() == Some(&DeltaTime(1.0)));
+    assert!(world.resource::<u64>() == Some(&7));
+
+    assert!(world.remove_resource::<DeltaTime>() == Some(DeltaTime(1.0)));
+    assert!(world.remove_resource::<DeltaTime>().is_none());
+    assert!(world.resource::<u64>() == Some(&7));
+}
+
+#[test]
+fn test_world_run_system_for_range() {
+    let mut world: World = World::init();
+
+    let mut spawn = |one: u32| {
+        let bundle: ComponentBundle = ComponentBundle::default()
+            .insert(TestCompA {
+                one,
+                ..Default::default()
+            })
+            .insert(TestCompC::default());
+        world.spawn(bundle).unwrap()
+    };
+
+    spawn(0);
+    let batch: Vec<_> = (1..=5).map(&mut spawn).collect();
+    spawn(6);
+
+    let id_range = batch[0].index()..batch[4].index() + 1;
+    let mut seen: Vec<u32> = Vec::new();
+    world.run_system_for_range::<TestReadOnly, _>(id_range, &mut |row: TestReadOnly| {
+        seen.push(row.comp_a.one);
+    });
+
+    assert!(seen == vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_world_partial_migrate() {
+    let mut world: World = World::init();
+
+    let entities: Vec<_> = (0..10)
+        .map(|_| {
+            world
+                .spawn(ComponentBundle::default().insert(TestCompA::default()))
+                .unwrap()
+        })
+        .collect();
+
+    let migrated: usize = world
+        .partial_migrate(
+            &entities,
+            |entity| entity.index() < 4,
+            || Migration::Add1(Frozen.into()),
+        )
+        .unwrap();
+
+    assert!(migrated == 4);
+    for entity in entities.iter() {
+        let frozen: bool = world
+            .get_archetype_for_entity_ref(*entity)
+            .unwrap()
+            .has_type(TypeId::of::<Frozen>());
+        assert!(frozen == (entity.index() < 4));
+    }
+}
+
+#[test]
+fn test_world_copy_entity_to_archetype() {
+    let mut world: World = World::init();
+    world.register_component::<TestCompA>();
+    world.register_component::<TestCompC>();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA {
+            one: 7,
+            ..Default::default()
+        })
+        .insert(TestCompB::default());
+    let source = world.spawn(bundle).unwrap();
+
+    let new_types: TypeBundle =
+        TypeBundle::from([TypeId::of::<TestCompA>(), TypeId::of::<TestCompC>()].as_slice());
+    let copy = world
+        .copy_entity_to_archetype(source, new_types.clone())
+        .unwrap();
+
+    assert!(world.get_archetype_for_entity_ref(copy).unwrap().types() == new_types);
+    assert!(
+        world.run_system_collecting::<TestReadOnly, _, _>(&mut |row: TestReadOnly| row.comp_a.one)
+            == vec![7]
+    );
+
+    let unregistered: TypeBundle = TypeBundle::from([TypeId::of::<TestCompB>()].as_slice());
+    assert!(world
+        .copy_entity_to_archetype(source, unregistered)
+        .is_err());
+}
+
+#[test]
+fn test_world_run_system_unless() {
+    let mut world: World = World::init();
+
+    let entities: Vec<_> = (0..4)
+        .map(|one| {
+            let bundle: ComponentBundle = ComponentBundle::default()
+                .insert(TestCompA {
+                    one,
+                    ..Default::default()
+                })
+                .insert(TestCompC::default());
+            world.spawn(bundle).unwrap()
+        })
+        .collect();
+
+    world
+        .migrate(entities[1], Migration::Add1(Frozen.into()))
+        .unwrap();
+
+    let mut seen: Vec<u32> = Vec::new();
+    world.run_system_unless::<TestReadOnly, _, Frozen>(&mut |row: TestReadOnly| {
+        seen.push(row.comp_a.one);
+    });
+    seen.sort();
+
+    assert!(seen == vec![0, 2, 3]);
+}
+
+#[derive(QueryModel)]
+pub struct TestHealth<'a> {
+    health: &'a Health,
+}
+
+#[test]
+fn test_world_merge_component_stores() {
+    let mut world: World = World::init();
+
+    let source = world
+        .spawn(ComponentBundle::default().insert(Health(3)))
+        .unwrap();
+    let target = world
+        .spawn(ComponentBundle::default().insert(Health(4)))
+        .unwrap();
+    let other = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(10))
+                .insert(TestCompA::default()),
+        )
+        .unwrap();
+
+    world
+        .merge_component_stores::<Health, _>(source, target, |a, b| Health(a.0 + b.0))
+        .unwrap();
+    world
+        .merge_component_stores::<Health, _>(other, source, |a, b| Health(a.0 + b.0))
+        .unwrap();
+
+    let mut healths: Vec<i32> = Vec::new();
+    for entity in [source, target, other] {
+        world.run_system_for_range::<TestHealth, _>(
+            entity.index()..entity.index() + 1,
+            &mut |row: TestHealth| healths.push(row.health.0),
+        );
+    }
+
+    assert!(healths == vec![13, 7, 10]);
+    assert!(world
+        .merge_component_stores::<TestCompB, _>(source, target, |_, b| b)
+        .is_ok());
+}
+
+#[derive(QueryModel)]
+pub struct TestPosition<'a> {
+    pos: &'a Position,
+}
+
+#[test]
+fn test_world_apply_initial_values() {
+    let mut world: World = World::init();
+
+    let entities: Vec<_> = (0..100)
+        .map(|_| {
+            world
+                .spawn(ComponentBundle::default().insert(Position::default()))
+                .unwrap()
+        })
+        .collect();
+
+    world
+        .apply_initial_values::<Position, _>(&entities, |entity| Position {
+            x: entity.index() as i32,
+            y: -(entity.index() as i32),
+        })
+        .unwrap();
+
+    let positions: Vec<Position> =
+        world.run_system_collecting::<TestPosition, _, _>(&mut |row: TestPosition| row.pos.clone());
+
+    assert!(positions.len() == 100);
+    for (entity, pos) in entities.iter().zip(positions.iter()) {
+        assert!(pos.x == entity.index() as i32 && pos.y == -pos.x);
+    }
+
+    assert!(world
+        .apply_initial_values::<Health, _>(&entities, |_| Health(0))
+        .is_err());
+}
+
+#[test]
+fn test_world_run_system_paginated() {
+    let mut world: World = World::init();
+
+    for x in 0..100 {
+        let mut bundle: ComponentBundle = ComponentBundle::default().insert(Position { x, y: 0 });
+        if x % 3 == 0 {
+            bundle = bundle.insert(Frozen);
+        }
+        world.spawn(bundle).unwrap();
+    }
+
+    let mut seen: Vec<i32> = Vec::new();
+    let mut page: usize = 0;
+    loop {
+        let processed: usize =
+            world.run_system_paginated::<TestPosition, _>(page, 10, &mut |row: TestPosition| {
+                seen.push(row.pos.x)
+            });
+        if processed == 0 {
+            break;
+        }
+        assert!(processed == 10);
+        page += 1;
+    }
+    seen.sort();
+
+    assert!(page == 10);
+    assert!(seen == (0..100).collect::<Vec<i32>>());
+}
+
+#[test]
+fn test_world_get_component_range() {
+    let mut world: World = World::init();
+
+    for health in 0..5 {
+        world
+            .spawn(ComponentBundle::default().insert(Health(health)))
+            .unwrap();
+    }
+
+    let guard = world.get_component_range::<Health>(1).unwrap();
+    let healths: &Vec<Health> = guard.to_any().downcast_ref::<Vec<Health>>().unwrap();
+
+    assert!(healths.len() == world.get_archetype(1).unwrap().len());
+    assert!(healths.iter().map(|health| health.0).sum::<i32>() == 10);
+    assert!(matches!(
+        world.get_component_range::<Position>(1),
+        Err(EcsError::Store(StoreError::StorageNotFound))
+    ));
+    assert!(matches!(
+        world.get_component_range::<Health>(5),
+        Err(EcsError::ArchetypeNotFound)
+    ));
+    assert!(matches!(
+        world.get_component_range::<Rare>(1),
+        Err(EcsError::Store(StoreError::WrongStorageKind))
+    ));
+}
+
+#[test]
+fn test_world_spawn_after_iter_boxes() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA::default())
+        .insert(TestCompC::default());
+    let has_c: bool = bundle
+        .iter_boxes()
+        .any(|comp| comp.inner_type_id() == TypeId::of::<TestCompC>());
+
+    assert!(has_c);
+    world.spawn(bundle).unwrap();
+    assert!(
+        world
+            .run_system_collecting::<TestReadOnly, _, _>(&mut |_: TestReadOnly| ())
+            .len()
+            == 1
+    );
+}
+
+#[derive(QueryModel)]
+pub struct TestChildren<'a> {
+    children: &'a Children,
+}
+
+#[test]
+fn test_world_kill_children_of() {
+    let mut world: World = World::init();
+
+    let children: Vec<_> = (0..3)
+        .map(|health| {
+            world
+                .spawn(ComponentBundle::default().insert(Health(health)))
+                .unwrap()
+        })
+        .collect();
+    let sibling = world
+        .spawn(ComponentBundle::default().insert(Health(3)))
+        .unwrap();
+    let parent = world
+        .spawn(ComponentBundle::default().insert(Children(children.clone())))
+        .unwrap();
+
+    assert!(world.kill_children_of(parent).unwrap() == 3);
+    for child in children.iter() {
+        assert!(world.get_archetype_for_entity_ref(*child).is_err());
+    }
+    assert!(world.get_archetype_for_entity_ref(sibling).unwrap().len() == 1);
+
+    let remaining: Vec<Children> = world
+        .run_system_collecting::<TestChildren, _, _>(&mut |row: TestChildren| row.children.clone());
+    assert!(remaining == vec![Children::default()]);
+
+    assert!(world.kill_children_of(parent).unwrap() == 0);
+    assert!(world.kill_children_of(sibling).unwrap() == 0);
+}
+
+#[test]
+fn test_world_replace_component() {
+    let mut world: World = World::init();
+
+    let entity = world
+        .spawn(ComponentBundle::default().insert(Position { x: 1, y: 2 }))
+        .unwrap();
+
+    let old = world
+        .replace_component(entity, Position { x: 3, y: 4 })
+        .unwrap();
+    let archetype: usize = world.iter_entity_archetypes().next().unwrap().1;
+
+    assert!(old == Some(Position { x: 1, y: 2 }));
+    assert!(archetype == 1);
+
+    assert!(world
+        .replace_component(entity, Health(5))
+        .unwrap()
+        .is_none());
+    assert!(world
+        .get_archetype_for_entity_ref(entity)
+        .unwrap()
+        .has_type(TypeId::of::<Health>()));
+
+    let positions: Vec<Position> =
+        world.run_system_collecting::<TestPosition, _, _>(&mut |row: TestPosition| row.pos.clone());
+    assert!(positions == vec![Position { x: 3, y: 4 }]);
+}
+
+#[test]
+fn test_world_get_component_count_by_type_id() {
+    let mut world: World = World::init();
+
+    for health in 0..3 {
+        let bundle: ComponentBundle = ComponentBundle::default()
+            .insert(Health(health))
+            .insert(TestCompA::default());
+        world.spawn(bundle).unwrap();
+    }
+    world
+        .spawn(ComponentBundle::default().insert(Health(3)))
+        .unwrap();
+    world
+        .spawn(ComponentBundle::default().insert(TestCompA::default()))
+        .unwrap();
+
+    assert!(world.get_component_count_by_type_id(TypeId::of::<Health>()) == 4);
+    assert!(
+        world.get_component_count_by_type_id(TypeId::of::<TestCompA>())
+            == world.get_component_count::<TestCompA>()
+    );
+    assert!(world.get_component_count::<TestCompA>() == 4);
+    assert!(world.get_component_count_by_type_id(TypeId::of::<Frozen>()) == 0);
+}
+
+#[test]
+fn test_world_compact_archetypes() {
+    let mut world: World = World::init();
+    world.register_component::<Health>();
+
+    let mut spawn = |bundle: ComponentBundle| world.spawn(bundle).unwrap();
+    let large: Vec<_> = (0..10)
+        .map(|one| {
+            spawn(
+                ComponentBundle::default()
+                    .insert(TestCompA {
+                        one,
+                        ..Default::default()
+                    })
+                    .insert(Health(1)),
+            )
+        })
+        .collect();
+    let small: Vec<_> = (10..12)
+        .map(|one| {
+            spawn(ComponentBundle::default().insert(TestCompA {
+                one,
+                ..Default::default()
+            }))
+        })
+        .collect();
+    let lone = spawn(ComponentBundle::default().insert(Health(2)));
+    let unrelated = spawn(ComponentBundle::default().insert(TestCompB::default()));
+
+    assert!(world.archetype_count() == 5);
+
+    assert!(world.compact_archetypes(5) == 1);
+    assert!(world.archetype_count() == 4);
+    assert!(world.compact_archetypes(5) == 0);
+    world.validate();
+
+    let target: TypeBundle =
+        TypeBundle::from([TypeId::of::<TestCompA>(), TypeId::of::<Health>()].as_slice());
+    for entity in large.iter().chain(small.iter()) {
+        assert!(world.get_archetype_for_entity_ref(*entity).unwrap().types() == target);
+    }
+    assert!(world.get_archetype_for_entity_ref(lone).unwrap().len() == 1);
+    assert!(world.get_archetype_for_entity_ref(unrelated).unwrap().len() == 1);
+    assert!(world.get_component_count::<Health>() == 13);
+}
+
+#[derive(Clone)]
+struct EnemyBundle {
+    pos: Position,
+    health: Health,
+}
+
+impl From<EnemyBundle> for ComponentBundle {
+    fn from(value: EnemyBundle) -> Self {
+        ComponentBundle::default()
+            .insert(value.pos)
+            .insert(value.health)
+    }
+}
+
+#[test]
+fn test_world_spawn_n() {
+    let mut world: World = World::init();
+
+    let template: EnemyBundle = EnemyBundle {
+        pos: Position { x: 1, y: 1 },
+        health: Health(10),
+    };
+    let entities = world.spawn_n(5, template).unwrap();
+
+    assert!(entities.len() == 5);
+    let healths: Vec<i32> =
+        world.run_system_collecting::<TestHealth, _, _>(&mut |row: TestHealth| row.health.0);
+    assert!(healths == vec![10; 5]);
+    assert!(world.get_component_count::<Position>() == 5);
+
+    let prefab: Prefab = Prefab::default().insert(Health(3)).insert(Frozen);
+    assert!(world.spawn_n(3, prefab).unwrap().len() == 3);
+    assert!(world.get_component_count::<Frozen>() == 3);
+}
+
+#[test]
+fn test_world_get_row_count() {
+    let mut world: World = World::init();
+
+    let mut entities: Vec<_> = (0..6)
+        .map(|health| {
+            let mut bundle: ComponentBundle = ComponentBundle::default().insert(Health(health));
+            if health % 2 == 0 {
+                bundle = bundle.insert(Position::default());
+            }
+            world.spawn(bundle).unwrap()
+        })
+        .collect();
+    entities.push(world.spawn(ComponentBundle::default()).unwrap());
+    assert!(world.get_row_count() == 7);
+    world.validate();
+
+    for entity in entities.drain(..3) {
+        world.kill(entity).unwrap();
+        assert!(world.get_row_count() == world.entity_count());
+    }
+    world
+        .migrate(entities[0], Migration::Add1(Frozen.into()))
+        .unwrap();
+
+    assert!(world.get_row_count() == 4);
+    world.validate();
+}
+
+#[test]
+fn test_world_run_system_on_newest() {
+    let mut world: World = World::init();
+
+    let entities: Vec<_> = (0..10)
+        .map(|health| {
+            let mut bundle: ComponentBundle = ComponentBundle::default().insert(Health(health));
+            if health % 2 == 0 {
+                bundle = bundle.insert(Frozen);
+            }
+            world.spawn(bundle).unwrap()
+        })
+        .collect();
+
+    let mut seen: Vec<i32> = Vec::new();
+    world.run_system_on_newest::<TestHealth, _>(3, &mut |row: TestHealth| {
+        seen.push(row.health.0);
+    });
+    seen.sort();
+
+    assert!(seen == vec![7, 8, 9]);
+    assert!(entities[9].index() == 9);
+}
+
+#[test]
+fn test_world_spawn_batch() {
+    let mut world: World = World::init();
+
+    let first = world
+        .spawn(ComponentBundle::default().insert(Health(-1)))
+        .unwrap();
+    world.kill(first).unwrap();
+
+    let bundles: Vec<ComponentBundle> = (0..1000)
+        .map(|health| {
+            ComponentBundle::default()
+                .insert(Health(health))
+                .insert(Position::default())
+        })
+        .collect();
+    let entities = world.spawn_batch(bundles).unwrap();
+
+    assert!(entities.len() == 1000);
+    assert!(world.entity_count() == 1000);
+    world.validate();
+
+    let mut healths: Vec<i32> =
+        world.run_system_collecting::<TestHealth, _, _>(&mut |row: TestHealth| row.health.0);
+    healths.sort();
+    assert!(healths == (0..1000).collect::<Vec<i32>>());
+    for (row, entity) in entities.iter().enumerate() {
+        let archetype: &Archetype = world.get_archetype_for_entity_ref(*entity).unwrap();
+        assert!(archetype.get_entity(row) == Some(*entity));
+    }
+
+    let mismatched: Vec<ComponentBundle> = vec![
+        ComponentBundle::default().insert(Health(0)),
+        ComponentBundle::default().insert(Position::default()),
+    ];
+    assert!(matches!(
+        world.spawn_batch(mismatched),
+        Err(EcsError::Store(StoreError::TypeMismatch))
+    ));
+    assert!(world.entity_count() == 1000);
+    world.validate();
+}
+
+#[test]
+fn test_world_get_component() {
+    let mut world: World = World::init();
+
+    let fighter = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(10))
+                .insert(Position::default()),
+        )
+        .unwrap();
+    let bystander = world
+        .spawn(ComponentBundle::default().insert(Health(4)))
+        .unwrap();
+
+    *world.get_component_mut::<Health>(fighter).unwrap() = Health(7);
+    world.get_component_mut::<Position>(fighter).unwrap().x += 2;
+
+    assert!(*world.get_component::<Health>(fighter).unwrap() == Health(7));
+    assert!(world.get_component::<Position>(fighter).unwrap().x == 2);
+    assert!(*world.get_component::<Health>(bystander).unwrap() == Health(4));
+    assert!(world.get_component::<Position>(bystander).is_none());
+    assert!(world.get_component_mut::<Position>(bystander).is_none());
+
+    world.kill(fighter).unwrap();
+    assert!(world.get_component::<Health>(fighter).is_none());
+    assert!(world.get_component_mut::<Health>(fighter).is_none());
+}
+
+#[test]
+fn test_world_kill_returns_components() {
+    let mut world: World = World::init();
+
+    let survivor = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(9))
+                .insert(Position { x: 1, y: 1 }),
+        )
+        .unwrap();
+    let fallen = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(0))
+                .insert(Position { x: 4, y: -2 }),
+        )
+        .unwrap();
+
+    let mut remains: ComponentBundle = world.kill(fallen).unwrap();
+
+    assert!(remains.remove_typed::<Health>().unwrap() == Health(0));
+    assert!(remains.remove_typed::<Position>().unwrap() == Position { x: 4, y: -2 });
+    assert!(*world.get_component::<Health>(survivor).unwrap() == Health(9));
+}
+
+#[test]
+fn test_world_query_without() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(TestCompA::default())
+        .insert(TestCompB::default());
+    let both = world.spawn(bundle).unwrap();
+    let only_a = world
+        .spawn(ComponentBundle::default().insert(TestCompA::default()))
+        .unwrap();
+
+    world.run_system::<TestWithoutB, _>(&mut |row: TestWithoutB| {
+        row.comp_a.one += 1;
+    });
+
+    assert!(world.get_component::<TestCompA>(both).unwrap().one == 0);
+    assert!(world.get_component::<TestCompA>(only_a).unwrap().one == 1);
+}
+
+#[test]
+fn test_world_has_component() {
+    let mut world: World = World::init();
+
+    let entity = world
+        .spawn(ComponentBundle::default().insert(Health(1)))
+        .unwrap();
+
+    assert!(world.has_component::<Health>(entity).unwrap());
+    assert!(!world.has_component::<Position>(entity).unwrap());
+
+    world.kill(entity).unwrap();
+    assert!(matches!(
+        world.has_component::<Health>(entity),
+        Err(EntityError::NotFound)
+    ));
+}
+
+#[test]
+fn test_world_migrate_to_new_archetype() {
+    let mut world: World = World::init();
+
+    let bundle = || {
+        ComponentBundle::default()
+            .insert(Health(5))
+            .insert(Position { x: 3, y: 4 })
+    };
+    let added = world.spawn(bundle()).unwrap();
+    let removed = world.spawn(bundle()).unwrap();
+
+    world
+        .migrate(added, Migration::Add1(Frozen.into()))
+        .unwrap();
+    let with_frozen: TypeBundle = bundle().types().add_type(TypeId::of::<Frozen>());
+    assert!(world.get_archetype_for_entity_ref(added).unwrap().types() == with_frozen);
+    assert!(world.has_component::<Frozen>(added).unwrap());
+    assert!(*world.get_component::<Health>(added).unwrap() == Health(5));
+
+    world
+        .migrate(removed, Migration::Remove(TypeId::of::<Health>()))
+        .unwrap();
+    let without_health: TypeBundle = TypeBundle::from([TypeId::of::<Position>()].as_slice());
+    assert!(world.get_archetype_for_entity_ref(removed).unwrap().types() == without_health);
+    assert!(!world.has_component::<Health>(removed).unwrap());
+    assert!(*world.get_component::<Position>(removed).unwrap() == Position { x: 3, y: 4 });
+    world.validate();
+}
+
+#[test]
+fn test_world_migrate_many() {
+    let mut world: World = World::init();
+
+    let entities: Vec<_> = (0..50)
+        .map(|health| {
+            world
+                .spawn(
+                    ComponentBundle::default()
+                        .insert(Health(health))
+                        .insert(Position::default()),
+                )
+                .unwrap()
+        })
+        .collect();
+    let others: Vec<_> = (0..5)
+        .map(|health| {
+            world
+                .spawn(ComponentBundle::default().insert(Health(health)))
+                .unwrap()
+        })
+        .collect();
+    let tagged: Vec<_> = entities
+        .iter()
+        .step_by(2)
+        .chain(others.iter())
+        .copied()
+        .collect();
+
+    world
+        .migrate_many(&tagged, || Migration::Add1(Frozen.into()))
+        .unwrap();
+
+    world.validate();
+    assert!(world.get_component_count::<Frozen>() == 30);
+    for (idx, &entity) in entities.iter().enumerate() {
+        assert!(world.has_component::<Frozen>(entity).unwrap() == (idx % 2 == 0));
+        assert!(*world.get_component::<Health>(entity).unwrap() == Health(idx as i32));
+    }
+    for &entity in others.iter() {
+        assert!(world.has_component::<Frozen>(entity).unwrap());
+        assert!(!world.has_component::<Position>(entity).unwrap());
+    }
+}
+
+#[test]
+fn test_world_query_changed() {
+    let mut world: World = World::init();
+
+    for health in 0..3 {
+        world
+            .spawn(
+                ComponentBundle::default()
+                    .insert(Health(health))
+                    .insert(Position::default()),
+            )
+            .unwrap();
+    }
+
+    let count_changed = |world: &mut World| {
+        world
+            .run_system_collecting::<TestChangedPosition, _, _>(&mut |row: TestChangedPosition| {
+                row.pos.x
+            })
+            .len()
+    };
+
+    assert!(count_changed(&mut world) == 3);
+    assert!(count_changed(&mut world) == 0);
+
+    world.run_system::<TestMovePosition, _>(&mut |row: TestMovePosition| row.pos.x += 1);
+    assert!(count_changed(&mut world) == 3);
+    assert!(count_changed(&mut world) == 0);
+
+    world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(3))
+                .insert(Position::default()),
+        )
+        .unwrap();
+    assert!(count_changed(&mut world) == 1);
+
+    let mut healed: usize = 0;
+    world.run_system::<TestChangedHealth, _>(&mut |mut row: TestChangedHealth| {
+        row.health.0 += 10;
+        healed += 1;
+    });
+    assert!(healed == 4);
+
+    healed = 0;
+    world.run_system::<TestChangedHealth, _>(&mut |_: TestChangedHealth| healed += 1);
+    assert!(healed == 0);
+}
+
+#[derive(QueryModel)]
+pub struct TestHealthOwner<'a> {
+    health: &'a Health,
+    children: &'a Children,
+}
+
+#[test]
+fn test_world_flush_commands() {
+    let mut world: World = World::init();
+
+    let doomed = world
+        .spawn(ComponentBundle::default().insert(Health(0)))
+        .unwrap();
+    let spawner = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(5))
+                .insert(Children(vec![doomed])),
+        )
+        .unwrap();
+
+    let mut commands: Vec<WorldCommand> = Vec::new();
+    world.run_system::<TestHealthOwner, _>(&mut |row: TestHealthOwner| {
+        for &child in row.children.0.iter() {
+            commands.push(WorldCommand::Kill(child));
+        }
+        commands.push(WorldCommand::Spawn(
+            ComponentBundle::default().insert(Health(row.health.0)),
+        ));
+    });
+    commands.push(WorldCommand::Migrate(
+        spawner,
+        Migration::Add1(Frozen.into()),
+    ));
+    world.flush_commands(commands).unwrap();
+
+    assert!(world.get_archetype_for_entity_ref(doomed).is_err());
+    assert!(world.has_component::<Frozen>(spawner).unwrap());
+    assert!(world.entity_count() == 2);
+    world.validate();
+}
+
+#[test]
+fn test_world_counts() {
+    let mut world: World = World::init();
+
+    assert!(world.archetype_count() == 1);
+    assert!(world.entity_count() == 0);
+
+    let first = world
+        .spawn(ComponentBundle::default().insert(Health(1)))
+        .unwrap();
+    world
+        .spawn(ComponentBundle::default().insert(Position::default()))
+        .unwrap();
+    world
+        .spawn(ComponentBundle::default().insert(Health(2)))
+        .unwrap();
+
+    assert!(world.archetype_count() == 3);
+    assert!(world.entity_count() == 3);
+
+    world.kill(first).unwrap();
+    assert!(world.archetype_count() == 3);
+    assert!(world.entity_count() == 2);
+}
+
+#[test]
+fn test_world_iter_entities() {
+    let mut world: World = World::init();
+
+    let mut spawned: Vec<_> = (0..4)
+        .map(|health| {
+            world
+                .spawn(ComponentBundle::default().insert(Health(health)))
+                .unwrap()
+        })
+        .collect();
+    spawned.push(
+        world
+            .spawn(ComponentBundle::default().insert(Position::default()))
+            .unwrap(),
+    );
+    world.kill(spawned.remove(1)).unwrap();
+
+    let mut entities: Vec<_> = world.iter_entities().collect();
+    entities.sort_by_key(|entity| entity.index());
+    spawned.sort_by_key(|entity| entity.index());
+
+    assert!(entities == spawned);
+}
+
+#[test]
+fn test_world_iter_entities_without_components() {
+    let mut world: World = World::init();
+    let empty: EntityId = world.spawn(ComponentBundle::default()).unwrap();
+    let stripped: EntityId = world.spawn_one(Health(1)).unwrap();
+    world.migrate_remove::<Health>(stripped).unwrap();
+    world.spawn_one(Health(2)).unwrap();
+
+    let entities: Vec<EntityId> = world.iter_entities().collect();
+    assert!(entities.len() == world.entity_count());
+    assert!(entities.len() == world.iter_entity_archetypes().count());
+    assert!(entities.contains(&empty) && entities.contains(&stripped));
+}
+
+#[test]
+fn test_world_migrate_typed() {
+    let mut world: World = World::init();
+
+    let entities: Vec<EntityId> = (0..4)
+        .map(|health| {
+            world
+                .spawn(
+                    ComponentBundle::default()
+                        .insert(Health(health))
+                        .insert(Position::default()),
+                )
+                .unwrap()
+        })
+        .collect();
+
+    world.migrate_add(entities[0], Frozen).unwrap();
+    world.migrate_add_many(&entities[1..3], Frozen).unwrap();
+    world.validate();
+    assert!(world.get_component_count::<Frozen>() == 3);
+    assert!(!world.has_component::<Frozen>(entities[3]).unwrap());
+
+    world.migrate_remove::<Health>(entities[0]).unwrap();
+    world
+        .migrate_remove_many::<Health>(&entities[1..3])
+        .unwrap();
+    world.validate();
+    assert!(!world.has_component::<Health>(entities[0]).unwrap());
+    assert!(world.has_component::<Frozen>(entities[0]).unwrap());
+    assert!(world.has_component::<Health>(entities[3]).unwrap());
+    assert!(world.get_component_count::<Health>() == 1);
+    assert!(world.get_component_count::<Position>() == 4);
+}
+
+#[test]
+fn test_world_migrate_to_lower_archetype() {
+    let mut world: World = World::init();
+
+    let entities: Vec<EntityId> = (0..3)
+        .map(|health| {
+            world
+                .spawn(ComponentBundle::default().insert(Health(health)))
+                .unwrap()
+        })
+        .collect();
+    world.migrate_add_many(&entities, Frozen).unwrap();
+
+    world.migrate_remove::<Frozen>(entities[0]).unwrap();
+    world.migrate_remove_many::<Frozen>(&entities[1..]).unwrap();
+
+    world.validate();
+    assert!(world.get_component_count::<Frozen>() == 0);
+    for (idx, &entity) in entities.iter().enumerate() {
+        assert!(*world.get_component::<Health>(entity).unwrap() == Health(idx as i32));
+    }
+}
+
+#[test]
+fn test_world_migrate_back_and_forth_to_lower_archetype() {
+    let mut world: World = World::init();
+
+    let existing: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(0))
+                .insert(Position::default()),
+        )
+        .unwrap();
+    let entity: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(1))
+                .insert(Position::default())
+                .insert(Frozen),
+        )
+        .unwrap();
+    let lower: usize = world
+        .iter_entity_archetypes()
+        .find(|&(id, _)| id == existing)
+        .unwrap()
+        .1;
+
+    // the first removal resolves the target by index, the second through the cached edge
+    for _ in 0..2 {
+        world.migrate_remove::<Frozen>(entity).unwrap();
+        world.validate();
+        assert!(!world.has_component::<Frozen>(entity).unwrap());
+        assert!(
+            world
+                .iter_entity_archetypes()
+                .find(|&(id, _)| id == entity)
+                .unwrap()
+                .1
+                == lower
+        );
+
+        world.migrate_add(entity, Frozen).unwrap();
+        world.validate();
+        assert!(world.has_component::<Frozen>(entity).unwrap());
+    }
+
+    assert!(*world.get_component::<Health>(entity).unwrap() == Health(1));
+    assert!(*world.get_component::<Health>(existing).unwrap() == Health(0));
+}
+
+#[test]
+fn test_world_migrate_add_bundle() {
+    let mut world: World = World::init();
+
+    let entity = world
+        .spawn(ComponentBundle::default().insert(Health(8)))
+        .unwrap();
+    let archetypes: usize = world.archetype_count();
+
+    let added: ComponentBundle = ComponentBundle::default()
+        .insert(Position { x: 2, y: 2 })
+        .insert(Frozen);
+    world.migrate(entity, Migration::Add(added)).unwrap();
+
+    assert!(world.archetype_count() == archetypes + 1);
+    assert!(
+        world.get_archetype_for_entity_ref(entity).unwrap().types()
+            == TypeBundle::from(
+                [
+                    TypeId::of::<Health>(),
+                    TypeId::of::<Position>(),
+                    TypeId::of::<Frozen>()
+                ]
+                .as_slice()
+            )
+    );
+    assert!(*world.get_component::<Health>(entity).unwrap() == Health(8));
+    assert!(*world.get_component::<Position>(entity).unwrap() == Position { x: 2, y: 2 });
+
+    let other = world
+        .spawn(ComponentBundle::default().insert(Health(1)))
+        .unwrap();
+    let added: ComponentBundle = ComponentBundle::default()
+        .insert(Position::default())
+        .insert(Frozen);
+    world.migrate(other, Migration::Add(added)).unwrap();
+
+    assert!(world.archetype_count() == archetypes + 1);
+    assert!(world.get_component_count::<Frozen>() == 2);
+    world.validate();
+}
+
+#[test]
+fn test_world_debug_summary() {
+    let mut world: World = World::init();
+    let archetypes: usize = world.archetype_count();
+    world
+        .spawn(ComponentBundle::default().insert(Health(1)))
+        .unwrap();
+    world
+        .spawn(ComponentBundle::default().insert(Health(2)).insert(Frozen))
+        .unwrap();
+    world.insert_resource(0.5_f32);
+
+    assert!(
+        format!("{world:?}")
+            == format!(
+                "World {{ archetypes: {}, entities: 2, resources: 1 }}",
+                archetypes + 2
+            )
+    );
+}
+
+#[test]
+fn test_world_sparse_component_keeps_archetype() {
+    let mut world: World = World::init();
+    let plain: EntityId = world.spawn_one(Health(1)).unwrap();
+    let rare: EntityId = world
+        .spawn(ComponentBundle::default().insert(Health(2)).insert(Rare(7)))
+        .unwrap();
+    let archetypes: usize = world.archetype_count();
+
+    world.migrate_add(plain, Rare(3)).unwrap();
+
+    assert!(world.archetype_count() == archetypes);
+    assert!(
+        world.get_archetype_for_entity(plain).unwrap().types()
+            == world.get_archetype_for_entity(rare).unwrap().types()
+    );
+    assert!(world.has_component::<Rare>(plain).unwrap());
+    assert!(*world.get_component::<Rare>(rare).unwrap() == Rare(7));
+    world.get_component_mut::<Rare>(plain).unwrap().0 += 1;
+    assert!(*world.get_component::<Rare>(plain).unwrap() == Rare(4));
+    assert!(world.get_component_count::<Rare>() == 2);
+    assert!(world.get_component_count::<Health>() == 2);
+    world.validate();
+}
+
+#[test]
+fn test_world_sparse_component_remove_and_kill() {
+    let mut world: World = World::init();
+    let kept: EntityId = world
+        .spawn(ComponentBundle::default().insert(Health(1)).insert(Rare(1)))
+        .unwrap();
+    let killed: EntityId = world
+        .spawn(ComponentBundle::default().insert(Health(2)).insert(Rare(2)))
+        .unwrap();
+
+    assert!(matches!(
+        world.migrate_add(kept, Rare(5)),
+        Err(EcsError::Store(StoreError::DuplicateType))
+    ));
+    let mut remains: ComponentBundle = world.kill(killed).unwrap();
+    assert!(remains.remove_typed::<Rare>().unwrap() == Rare(2));
+    assert!(*world.get_component::<Rare>(kept).unwrap() == Rare(1));
+
+    world.migrate_remove::<Rare>(kept).unwrap();
+    assert!(!world.has_component::<Rare>(kept).unwrap());
+    assert!(*world.get_component::<Health>(kept).unwrap() == Health(1));
+    assert!(world.get_component_count::<Rare>() == 0);
+    assert!(matches!(
+        world.migrate_remove::<Rare>(kept),
+        Err(EcsError::Store(StoreError::TypeNotFound))
+    ));
+
+    let reused: EntityId = world.spawn_one(Health(3)).unwrap();
+    assert!(world.get_component::<Rare>(reused).is_none());
+}
+
+#[test]
+fn test_world_inclusive_index_matches_scan() {
+    let mut world: World = World::init();
+    world.spawn_one(Health(1)).unwrap();
+    world.spawn_one(Frozen).unwrap();
+    let wide: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Health(2))
+                .insert(Frozen)
+                .insert(Position::default()),
+        )
+        .unwrap();
+    world
+        .spawn(ComponentBundle::default().insert(Health(3)).insert(Frozen))
+        .unwrap();
+    world.migrate_remove::<Position>(wide).unwrap();
+
+    let queries: [TypeBundle; 4] = [
+        TypeBundle::default(),
+        TypeBundle::from([TypeId::of::<Health>()].as_slice()),
+        TypeBundle::from([TypeId::of::<Frozen>()].as_slice()),
+        TypeBundle::from([TypeId::of::<Health>(), TypeId::of::<Frozen>()].as_slice()),
+    ];
+    for types in queries.iter() {
+        let scanned: Vec<usize> = (0..world.archetype_count())
+            .filter(|&idx| {
+                world
+                    .get_archetype(idx)
+                    .unwrap()
+                    .types()
+                    .is_superset_of(types)
+            })
+            .collect();
+        let indexed: Vec<usize> = world
+            .get_archetypes_inclusive(types)
+            .iter()
+            .map(|at| {
+                (0..world.archetype_count())
+                    .find(|&idx| std::ptr::eq(world.get_archetype(idx).unwrap(), *at))
+                    .unwrap()
+            })
+            .collect();
+
+        assert!(indexed == scanned, "{types:?}: {indexed:?} != {scanned:?}");
+    }
+}
+
+#[test]
+fn test_world_despawn_type() {
+    let mut world: World = World::init();
+    let bullets: Vec<EntityId> = (0..3)
+        .map(|_| {
+            world
+                .spawn(
+                    ComponentBundle::default()
+                        .insert(TestCompA::default())
+                        .insert(TestCompC::default()),
+                )
+                .unwrap()
+        })
+        .collect();
+    let tagged: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompB::default())
+                .insert(TestCompC::default())
+                .insert(Rare(1)),
+        )
+        .unwrap();
+    let survivor: EntityId = world.spawn_one(TestCompA::default()).unwrap();
+
+    assert!(world.despawn_type::<TestReadOnly>() == 4);
+
+    assert!(world.entity_count() == 1);
+    assert!(world.contains(survivor));
+    assert!(!world.contains(tagged));
+    assert!(bullets.iter().all(|&bullet| !world.contains(bullet)));
+    assert!(world.get_component_count::<Rare>() == 0);
+    assert!(world.despawn_type::<TestReadOnly>() == 0);
+
+    let respawned: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(TestCompA::default())
+                .insert(TestCompC::default()),
+        )
+        .unwrap();
+    assert!(world.get_component::<TestCompC>(respawned).is_some());
+    world.validate();
+
+    let frozen: EntityId = world
+        .spawn(ComponentBundle::default().insert(Health(1)).insert(Rare(1)))
+        .unwrap();
+    let thawed: EntityId = world.spawn_one(Health(2)).unwrap();
+    let mixed: EntityId = world.spawn_one(Health(3)).unwrap();
+    world.migrate_add(mixed, Rare(2)).unwrap();
+
+    assert!(world.despawn_type::<TestWithoutRare>() == 1);
+    assert!(world.contains(frozen) && world.contains(mixed) && !world.contains(thawed));
+    assert!(world.get_component_count::<Rare>() == 2);
+    world.validate();
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_world_run_system_parallel() {
+    let mut world: World = World::init();
+    let entities: Vec<EntityId> = (0..200)
+        .map(|idx| {
+            let mut bundle: ComponentBundle = ComponentBundle::default()
+                .insert(TestCompA {
+                    one: idx,
+                    ..Default::default()
+                })
+                .insert(TestCompB::default())
+                .insert(TestCompC::default());
+            if idx % 2 == 0 {
+                bundle.push(Frozen);
+            }
+            world.spawn(bundle).unwrap()
+        })
+        .collect();
+
+    let processed: usize =
+        world.run_system_parallel_chunked::<TestDataA, _>(7, |row: TestDataA| {
+            row.comp_b.three = row.comp_a.one as f32 * 2.0;
+        });
+
+    assert!(processed == entities.len());
+    for (idx, &entity) in entities.iter().enumerate() {
+        assert!(world.get_component::<TestCompB>(entity).unwrap().three == idx as f32 * 2.0);
+    }
+    assert!(
+        world.run_system_parallel::<TestDataA, _>(|row: TestDataA| row.comp_b.three += 1.0) == 200
+    );
+    assert!(world.run_system_parallel_chunked::<TestMovePosition, _>(0, |_| ()) == 0);
+}
+
+#[test]
+fn test_world_migrate_remove_last_row_keeps_locations() {
+    let mut world: World = World::init();
+    let entities: Vec<EntityId> = (0..3)
+        .map(|idx| {
+            world
+                .spawn(
+                    ComponentBundle::default()
+                        .insert(Health(idx))
+                        .insert(Position { x: idx, y: idx }),
+                )
+                .unwrap()
+        })
+        .collect();
+
+    // the last row migrates without swapping, then a middle row swaps the new last row into place
+    world.migrate_remove::<Position>(entities[2]).unwrap();
+    world.migrate_remove::<Position>(entities[0]).unwrap();
+
+    for (idx, &entity) in entities.iter().enumerate() {
+        assert!(*world.get_component::<Health>(entity).unwrap() == Health(idx as i32));
+    }
+    assert!(*world.get_component::<Position>(entities[1]).unwrap() == Position { x: 1, y: 1 });
+    assert!(world.get_component::<Position>(entities[0]).is_none());
+    assert!(world.get_component::<Position>(entities[2]).is_none());
+    world.validate();
+}
+
+#[test]
+fn test_world_insert_remove_component() {
+    let mut world: World = World::init();
+    let entity: EntityId = world.spawn_one(Health(5)).unwrap();
+
+    world.insert_component(entity, Frozen).unwrap();
+    assert!(world.has_component::<Frozen>(entity).unwrap());
+    assert!(matches!(
+        world.insert_component(entity, Frozen),
+        Err(EcsError::Store(StoreError::DuplicateType))
+    ));
+
+    world.remove_component::<Frozen>(entity).unwrap();
+    assert!(!world.has_component::<Frozen>(entity).unwrap());
+    assert!(*world.get_component::<Health>(entity).unwrap() == Health(5));
+    assert!(matches!(
+        world.remove_component::<Frozen>(entity),
+        Err(EcsError::Store(StoreError::TypeNotFound))
+    ));
+
+    world.kill(entity).unwrap();
+    assert!(matches!(
+        world.insert_component(entity, Frozen),
+        Err(EcsError::Entity(EntityError::NotFound))
+    ));
+    assert!(matches!(
+        world.remove_component::<Health>(entity),
+        Err(EcsError::Entity(EntityError::NotFound))
+    ));
+    world.validate();
+}
+
+#[derive(Debug, PartialEq)]
+struct Collision(u32);
+
+#[test]
+fn test_world_events_live_for_one_frame() {
+    let mut world: World = World::init();
+    assert!(world.read_events::<Collision>().is_empty());
+
+    world.send_event(Collision(1));
+    assert!(world.read_events::<Collision>() == [Collision(1)]);
+
+    world.next_frame();
+    world.send_event(Collision(2));
+    assert!(world.read_events::<Collision>() == [Collision(1), Collision(2)]);
+
+    world.next_frame();
+    assert!(world.read_events::<Collision>() == [Collision(2)]);
+
+    world.next_frame();
+    assert!(world.read_events::<Collision>().is_empty());
+}
+
+#[test]
+fn test_world_clear_events() {
+    let mut world: World = World::init();
+    world.send_event(Collision(1));
+    world.next_frame();
+    world.send_event(Collision(2));
+    world.send_event(Health(3));
+
+    world.clear_events::<Collision>();
+    world.clear_events::<Position>();
+
+    assert!(world.read_events::<Collision>().is_empty());
+    assert!(world.read_events::<Health>() == [Health(3)]);
+    world.send_event(Collision(4));
+    world.next_frame();
+    assert!(world.read_events::<Collision>() == [Collision(4)]);
+}
+
+#[test]
+fn test_world_swap_components() {
+    let mut world: World = World::init();
+    let a: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Position { x: 1, y: 1 })
+                .insert(Rare(1)),
+        )
+        .unwrap();
+    let b: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Position { x: 2, y: 2 })
+                .insert(Rare(2)),
+        )
+        .unwrap();
+    let c: EntityId = world
+        .spawn(
+            ComponentBundle::default()
+                .insert(Position { x: 3, y: 3 })
+                .insert(Frozen),
+        )
+        .unwrap();
+    let bare: EntityId = world.spawn_one(Health(1)).unwrap();
+
+    world.swap_components::<Position>(a, b).unwrap();
+    world.swap_components::<Position>(a, c).unwrap();
+    world.swap_components::<Rare>(a, b).unwrap();
+    world.swap_components::<Position>(b, b).unwrap();
+
+    assert!(*world.get_component::<Position>(a).unwrap() == Position { x: 3, y: 3 });
+    assert!(*world.get_component::<Position>(b).unwrap() == Position { x: 1, y: 1 });
+    assert!(*world.get_component::<Position>(c).unwrap() == Position { x: 2, y: 2 });
+    assert!(*world.get_component::<Rare>(a).unwrap() == Rare(2));
+    assert!(world.has_component::<Frozen>(c).unwrap());
+    assert!(matches!(
+        world.swap_components::<Position>(a, bare),
+        Err(EcsError::Store(StoreError::TypeNotFound))
+    ));
+    assert!(matches!(
+        world.swap_components::<Rare>(c, a),
+        Err(EcsError::Store(StoreError::TypeNotFound))
+    ));
+
+    world.kill(b).unwrap();
+    assert!(matches!(
+        world.swap_components::<Position>(a, b),
+        Err(EcsError::Entity(EntityError::NotFound))
+    ));
+    world.validate();
+}
+
+#[test]
+fn test_world_direct_writes_flag_changed() {
+    let mut world: World = World::init();
+    let a: EntityId = world.spawn_one(Health(1)).unwrap();
+    let b: EntityId = world.spawn_one(Health(2)).unwrap();
+
+    let count_changed = |world: &mut World| {
+        world.run_system::<TestChangedHealth, _>(&mut |_: TestChangedHealth| {})
+    };
+    assert!(count_changed(&mut world) == 2);
+
+    world.get_component_mut::<Health>(a).unwrap().0 = 5;
+    assert!(count_changed(&mut world) == 1);
+
+    assert!(*world.get_component_mut::<Health>(a).unwrap() == Health(5));
+    assert!(count_changed(&mut world) == 0);
+
+    world.replace_component(b, Health(6)).unwrap();
+    assert!(count_changed(&mut world) == 1);
+
+    world
+        .merge_component_stores::<Health, _>(a, b, |source, target| Health(source.0 + target.0))
+        .unwrap();
+    assert!(count_changed(&mut world) == 1);
+
+    world.apply_initial_values(&[a, b], |_| Health(0)).unwrap();
+    assert!(count_changed(&mut world) == 2);
+
+    world.swap_components::<Health>(a, b).unwrap();
+    assert!(count_changed(&mut world) == 2);
+}
+
+#[test]
+fn test_world_runners_apply_changed_filters() {
+    let mut world: World = World::init();
+    let entities: Vec<EntityId> = (0..4)
+        .map(|health| {
+            world
+                .spawn(
+                    ComponentBundle::default()
+                        .insert(Health(health))
+                        .insert(Position::default()),
+                )
+                .unwrap()
+        })
+        .collect();
+
+    let mut noop = |_: TestChangedHealth| {};
+    assert!(world.run_system_paginated::<TestChangedHealth, _>(0, 3, &mut noop) == 3);
+    assert!(world.run_system_paginated::<TestChangedHealth, _>(0, 3, &mut noop) == 1);
+    assert!(world.run_system::<TestChangedHealth, _>(&mut noop) == 0);
+
+    world
+        .apply_initial_values(&entities, |_| Health(1))
+        .unwrap();
+    assert!(world.run_system_on_newest::<TestChangedHealth, _>(1, &mut noop) == 1);
+    let range: std::ops::Range<u32> = entities[0].index()..entities[2].index();
+    assert!(world.run_system_for_range::<TestChangedHealth, _>(range.clone(), &mut noop) == 2);
+    assert!(world.run_system_unless::<TestChangedHealth, _, Frozen>(&mut noop) == 1);
+    assert!(world.run_system::<TestChangedHealth, _>(&mut noop) == 0);
+
+    world.run_system::<TestChangedPosition, _>(&mut |_: TestChangedPosition| {});
+    world.run_system_for_range::<TestMovePosition, _>(range, &mut |row: TestMovePosition| {
+        row.pos.x += 1
+    });
+    world.run_system_paginated::<TestMovePosition, _>(1, 1, &mut |row: TestMovePosition| {
+        row.pos.x += 1
+    });
+    assert!(world.run_system::<TestChangedPosition, _>(&mut |_: TestChangedPosition| {}) == 2);
+    world.run_system_unless::<TestMovePosition, _, Frozen>(&mut |row: TestMovePosition| {
+        row.pos.x += 1
+    });
+    world.run_system_on_newest::<TestMovePosition, _>(1, &mut |row: TestMovePosition| {
+        row.pos.x += 1
+    });
+    assert!(world.run_system::<TestChangedPosition, _>(&mut |_: TestChangedPosition| {}) == 4);
+}
+
+#[test]
+fn test_world_sparse_component_paths() {
+    let mut world: World = World::init();
+    world.register_component::<Health>();
+    world.register_component::<Rare>();
+
+    let a: EntityId = world
+        .spawn(ComponentBundle::default().insert(Health(1)).insert(Rare(1)))
+        .unwrap();
+    let b: EntityId = world
+        .spawn(ComponentBundle::default().insert(Health(2)).insert(Rare(2)))
+        .unwrap();
+    let plain: EntityId = world.spawn_one(Health(3)).unwrap();
+
+    assert!(world.replace_component(a, Rare(4)).unwrap() == Some(Rare(1)));
+    assert!(*world.get_component::<Rare>(a).unwrap() == Rare(4));
+    assert!(world.replace_component(plain, Rare(5)).unwrap().is_none());
+    world.migrate_remove::<Rare>(plain).unwrap();
+
+    let (spawned, rare): (EntityId, Rare) = world
+        .spawn_and_get::<Rare>(ComponentBundle::default().insert(Rare(6)))
+        .unwrap();
+    assert!(rare == Rare(6));
+    assert!(*world.get_component::<Rare>(spawned).unwrap() == Rare(6));
+    world.kill(spawned).unwrap();
+
+    world
+        .merge_component_stores::<Rare, _>(a, b, |source, target| Rare(source.0 + target.0))
+        .unwrap();
+    assert!(*world.get_component::<Rare>(b).unwrap() == Rare(6));
+    assert!(*world.get_component::<Rare>(a).unwrap() == Rare(4));
+    world
+        .merge_component_stores::<Rare, _>(plain, a, |_, _| Rare(0))
+        .unwrap();
+    assert!(*world.get_component::<Rare>(a).unwrap() == Rare(4));
+
+    world
+        .apply_initial_values(&[a, b], |entity| Rare(entity.index() * 10))
+        .unwrap();
+    assert!(*world.get_component::<Rare>(b).unwrap() == Rare(b.index() * 10));
+    assert!(matches!(
+        world.apply_initial_values(&[a, plain], |_| Rare(0)),
+        Err(EcsError::Store(StoreError::StorageNotFound))
+    ));
+    assert!(*world.get_component::<Rare>(a).unwrap() == Rare(a.index() * 10));
+
+    let types: TypeBundle =
+        TypeBundle::from([TypeId::of::<Health>(), TypeId::of::<Rare>()].as_slice());
+    let copy: EntityId = world.copy_entity_to_archetype(b, types.clone()).unwrap();
+    assert!(*world.get_component::<Rare>(copy).unwrap() == Rare(b.index() * 10));
+    let defaulted: EntityId = world.copy_entity_to_archetype(plain, types).unwrap();
+    assert!(*world.get_component::<Rare>(defaulted).unwrap() == Rare::default());
+    world.kill(copy).unwrap();
+    world.kill(defaulted).unwrap();
+
+    let mut seen: Vec<i32> = Vec::new();
+    world.run_system::<TestWithoutRare, _>(&mut |row: TestWithoutRare| seen.push(row.health.0));
+    assert!(seen == vec![3]);
+
+    let mut noop = |_: TestChangedHealth| {};
+    world.run_system::<TestChangedHealth, _>(&mut noop);
+    world.get_component_mut::<Health>(a).unwrap().0 = 4;
+    world.get_component_mut::<Health>(plain).unwrap().0 = 5;
+    assert!(world.run_system_unless::<TestChangedHealth, _, Rare>(&mut noop) == 1);
+    assert!(world.run_system::<TestChangedHealth, _>(&mut noop) == 1);
+    assert!(
+        world
+            .query_single::<TestWithoutRare, _, _>(|row| row.health.0)
+            .unwrap()
+            == 5
+    );
+    assert!(world.retain::<TestWithoutRare, _>(|_| false).unwrap() == 1);
+    assert!(world.contains(a) && world.contains(b) && !world.contains(plain));
+    world.validate();
+}
+
+#[test]
+fn test_world_type_names_shared_across_threads() {
+    let mut world: World = World::init();
+    world.spawn_one(Position::default()).unwrap();
+
+    let name: String =
+        std::thread::spawn(|| TypeBundle::from([TypeId::of::<Position>()].as_slice()).to_string())
+            .join()
+            .unwrap();
+    assert!(name == "Position");
+}
+
+#[derive(QueryModel)]
+pub struct TestReadsRare<'a> {
+    health: &'a Health,
+    rare: &'a Rare,
+}
+
+#[test]
+#[should_panic(expected = "reads or writes sparse components (Rare)")]
+fn test_world_query_reading_sparse_panics() {
+    let mut world: World = World::init();
+    world
+        .spawn(ComponentBundle::default().insert(Health(1)).insert(Rare(1)))
+        .unwrap();
+
+    world.run_system::<TestReadsRare, _>(&mut |row: TestReadsRare| {
+        let _ = (row.health, row.rare);
+    });
+}
+
+#[test]
+#[should_panic(expected = "reads or writes sparse components")]
+fn test_world_register_query_reading_sparse_panics() {
+    World::init().register_query::<TestReadsRare>();
+}
+
+#[test]
+fn test_world_changed_single_consumer() {
+    let mut world: World = World::init();
+    world.spawn_one(Health(1)).unwrap();
+    world.spawn_one(Health(2)).unwrap();
+
+    let mut first: Vec<i32> = Vec::new();
+    world.run_system::<TestReadChangedHealth, _>(&mut |row: TestReadChangedHealth| {
+        first.push(row.health.0)
+    });
+    first.sort();
+    assert!(first == vec![1, 2]);
+
+    let second: usize = world.run_system::<TestChangedHealth, _>(&mut |_: TestChangedHealth| {});
+    assert!(second == 0);
+}
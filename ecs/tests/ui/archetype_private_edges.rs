@@ -0,0 +1,8 @@
+use ecs::archetype::Archetype;
+use std::any::TypeId;
+
+fn main() {
+    let mut at = Archetype::default();
+    at.edges.insert(TypeId::of::<u32>(), usize::MAX);
+    at.set_edge(TypeId::of::<u32>(), usize::MAX);
+}
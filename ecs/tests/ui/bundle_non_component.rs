@@ -0,0 +1,14 @@
+use ecs_derive::{Bundle, Component};
+
+#[derive(Component)]
+struct Health(i32);
+
+struct NotAComponent;
+
+#[derive(Bundle)]
+struct BrokenBundle {
+    health: Health,
+    other: NotAComponent,
+}
+
+fn main() {}
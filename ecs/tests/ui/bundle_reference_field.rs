@@ -0,0 +1,11 @@
+use ecs_derive::{Bundle, Component};
+
+#[derive(Component)]
+struct Health(i32);
+
+#[derive(Bundle)]
+struct BorrowedBundle<'a> {
+    health: &'a Health,
+}
+
+fn main() {}
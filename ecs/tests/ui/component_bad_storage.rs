@@ -0,0 +1,7 @@
+use ecs_derive::Component;
+
+#[derive(Component)]
+#[component(storage = "packed")]
+struct Tagged(u32);
+
+fn main() {}
@@ -0,0 +1,12 @@
+use ecs_derive::Component;
+
+#[derive(Component)]
+struct Noisy(u32);
+
+impl Drop for Noisy {
+    fn drop(&mut self) {
+        println!("dropped {}", self.0);
+    }
+}
+
+fn main() {}
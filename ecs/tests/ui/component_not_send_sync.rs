@@ -0,0 +1,8 @@
+use std::rc::Rc;
+
+use ecs_derive::Component;
+
+#[derive(Component)]
+struct Shared(Rc<u32>);
+
+fn main() {}
@@ -0,0 +1,26 @@
+use ecs::{bundle::ComponentBundle, world::World};
+use ecs_derive::Component;
+
+#[derive(Component)]
+struct Pair<A, B>(A, B);
+
+#[derive(Component)]
+struct Bounded<T>
+where
+    T: Clone,
+{
+    value: T,
+}
+
+fn main() {
+    let mut world: World = World::init();
+
+    let bundle: ComponentBundle = ComponentBundle::default()
+        .insert(Pair(1u8, String::from("pair")))
+        .insert(Bounded { value: 2.0f32 });
+    let entity = world.spawn(bundle).unwrap();
+
+    let pair = world.get_component::<Pair<u8, String>>(entity).unwrap();
+    assert!(pair.0 == 1 && pair.1 == "pair");
+    assert!(world.get_component::<Bounded<f32>>(entity).unwrap().value == 2.0);
+}
@@ -0,0 +1,9 @@
+use ecs::bundle::Prefab;
+use ecs_derive::Component;
+
+#[derive(Component)]
+struct Unique(i32);
+
+fn main() {
+    let _prefab: Prefab = Prefab::default().insert(Unique(1));
+}
@@ -0,0 +1,9 @@
+use ecs_derive::QueryModel;
+
+#[derive(QueryModel)]
+enum NotAQuery {
+    First,
+    Second,
+}
+
+fn main() {}
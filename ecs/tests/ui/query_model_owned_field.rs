@@ -0,0 +1,15 @@
+use ecs_derive::{Component, QueryModel};
+
+#[derive(Component)]
+struct Position(f32);
+
+#[derive(Component)]
+struct Velocity(f32);
+
+#[derive(QueryModel)]
+struct BadQuery<'a> {
+    pos: Position,
+    vel: &'a Velocity,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use ecs_derive::{Component, QueryModel};
+
+#[derive(Component)]
+struct Position(f32);
+
+#[derive(QueryModel)]
+struct TaggedQuery<'a>(&'a Position, u32);
+
+fn main() {}
@@ -1,19 +1,37 @@
+#![allow(dead_code)]
+
 use ecs_derive::Component;
 
-#[derive(Component, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug, Clone)]
 pub struct TestCompA {
     pub one: u32,
     pub two: String,
 }
 
-#[derive(Component, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug, Clone)]
 pub struct TestCompB {
     pub three: f32,
     pub four: String,
 }
 
-#[derive(Component, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug, Clone)]
 pub struct TestCompC {
     pub five: Vec<usize>,
     pub six: String,
 }
+
+#[derive(Component, Default, PartialEq, Debug, Clone)]
+pub struct Health(pub i32);
+
+#[derive(Component, Default, PartialEq, Debug, Clone)]
+pub struct Frozen;
+
+#[derive(Component, Default, PartialEq, Debug, Clone)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Component, Default, PartialEq, Debug, Clone)]
+#[component(storage = "sparse")]
+pub struct Rare(pub u32);